@@ -0,0 +1,44 @@
+use crate::environment::{load_trust_store, lock_home, trust_file};
+use crate::shim;
+use crate::use_file;
+use anyhow::{bail, Context};
+use console::style;
+use std::env;
+
+pub fn run() -> anyhow::Result<()> {
+    let cwd = env::current_dir()?;
+    let use_files = use_file::load_all(use_file::find(&cwd))?;
+    if use_files.is_empty() {
+        bail!("no use file found in {} or its parents", cwd.display());
+    }
+
+    let _lock = lock_home()?;
+    let mut trust_store = load_trust_store()?;
+    for (path, file) in &use_files {
+        trust_store.allow(path, &file.digest);
+    }
+    trust_store
+        .save(&trust_file())
+        .context("failed to save trusted files")?;
+
+    for (path, file) in &use_files {
+        println!("Trusted {}", path.display());
+        for (command, versions) in file.resolved_defs(path) {
+            // Commands that are only defined by the project don't have a
+            // shim yet
+            shim::make_shim(&command, &env::current_exe()?)
+                .with_context(|| format!("failed to create shim for {command}"))?;
+            for (version, bin) in versions {
+                println!(
+                    " {} {} ({})",
+                    style(&command).bold(),
+                    version,
+                    bin.display()
+                );
+            }
+        }
+    }
+    println!("Changing these files will require running alt allow again.");
+
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use crate::command::{find_selected_version, find_system_bin, load_registry};
+use crate::command_version::CommandVersion;
+use crate::scan;
+use crate::scan_cmd::OutputFormat;
+use crate::use_file::{self, UseFile};
+use crate::version_order;
+use crate::version_req::VersionReq;
+use anyhow::Context;
+use console::style;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::env;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Exit code used when a version asked for by a use file can't be run. This
+/// lets scripts tell the difference between problems with the versions and
+/// an actual error.
+pub const PROBLEMS_EXIT_CODE: i32 = 4;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Ok,
+    /// No defined version matches the one asked for
+    Missing,
+    Disabled,
+    NotFound,
+    NotExecutable,
+}
+
+impl Status {
+    fn describe(self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Missing => "no matching version is defined",
+            Status::Disabled => "the version is disabled",
+            Status::NotFound => "the bin does not exist",
+            Status::NotExecutable => "the bin is not executable",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Suggestion {
+    version: String,
+    path: PathBuf,
+}
+
+#[derive(Serialize)]
+struct CheckEntry {
+    command: String,
+    requested: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    status: Status,
+    /// Versions found by the scanners that could be defined to fix the problem
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    suggestions: Vec<Suggestion>,
+}
+
+#[derive(Serialize)]
+struct CheckReport<'a> {
+    files: Vec<&'a Path>,
+    commands: Vec<CheckEntry>,
+}
+
+fn bin_status(path: &Path) -> Status {
+    match path.metadata() {
+        Err(_) => Status::NotFound,
+        Ok(metadata) if !metadata.is_file() || metadata.mode() & 0o111 == 0 => {
+            Status::NotExecutable
+        }
+        Ok(_) => Status::Ok,
+    }
+}
+
+/// Find the versions that were `found` on the machine that would satisfy the
+/// use file. Versions asked for by name are suggested under that name.
+fn suggest(found: &[CommandVersion], command: &str, requested: &str) -> Vec<Suggestion> {
+    let req = VersionReq::parse(requested);
+    let mut found: Vec<_> = found
+        .iter()
+        .filter(|found| found.command_name == command)
+        .cloned()
+        .collect();
+    found.sort();
+    found.dedup_by(|a, b| a.path == b.path);
+
+    found
+        .into_iter()
+        .filter_map(|found| {
+            let version = match &req {
+                VersionReq::Name(name)
+                    if *name == found.version_name
+                        || version_order::is_prefix(name, &found.version_name) == Some(true) =>
+                {
+                    name.clone()
+                }
+                VersionReq::Constraints(_) if req.matches(&found.version_name) => {
+                    found.version_name
+                }
+                _ => return None,
+            };
+            Some(Suggestion {
+                version,
+                path: found.path,
+            })
+        })
+        .collect()
+}
+
+fn check(use_files: &[(PathBuf, UseFile)]) -> anyhow::Result<Vec<CheckEntry>> {
+    let registry = load_registry(use_files)?;
+
+    let commands: BTreeSet<_> = use_files
+        .iter()
+        .flat_map(|(_, file)| file.versions.keys())
+        .collect();
+
+    // Scanning is slow, it's only done once and only when needed
+    let mut found = None;
+
+    let mut entries = vec![];
+    for command in commands {
+        let requested = use_file::requested_version(use_files, command).unwrap();
+        let selected = find_selected_version(&registry, use_files, command);
+
+        let (path, status) = match &selected {
+            Err(_) => (None, Status::Missing),
+            Ok(None) => {
+                let path = find_system_bin(command);
+                let status = path.as_deref().map_or(Status::NotFound, bin_status);
+                (path, status)
+            }
+            Ok(Some(version)) => match registry.get_entry(command, version) {
+                None => (None, Status::Missing),
+                Some(entry) if entry.disabled => (Some(entry.path.clone()), Status::Disabled),
+                Some(entry) => (Some(entry.path.clone()), bin_status(&entry.path)),
+            },
+        };
+        let is_system = matches!(selected, Ok(None));
+        let version = selected.ok().flatten();
+
+        let suggestions = match status {
+            Status::Ok => vec![],
+            _ if is_system => vec![],
+            _ => {
+                let found = found.get_or_insert_with(|| scan::scan(None).versions);
+                suggest(found, command, requested)
+            }
+        };
+
+        entries.push(CheckEntry {
+            command: command.clone(),
+            requested: requested.to_owned(),
+            // A version that doesn't match anything isn't a version
+            version: version.filter(|_| status != Status::Missing),
+            path,
+            status,
+            suggestions,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn print_entries(entries: &[CheckEntry]) {
+    for entry in entries {
+        let mark = match entry.status {
+            Status::Ok => style("✓").green().bold(),
+            _ => style("✗").red().bold(),
+        };
+        let version = match &entry.version {
+            Some(version) if *version != entry.requested => {
+                format!("{} ({})", entry.requested, version)
+            }
+            _ => entry.requested.clone(),
+        };
+        let path = entry
+            .path
+            .as_ref()
+            .map(|path| format!(" {}", path.display()))
+            .unwrap_or_default();
+
+        if entry.status == Status::Ok {
+            println!(" {} {} {}{}", mark, entry.command, version, path);
+        } else {
+            println!(
+                " {} {} {}{}: {}",
+                mark,
+                entry.command,
+                version,
+                path,
+                entry.status.describe()
+            );
+        }
+
+        for suggestion in &entry.suggestions {
+            println!(
+                "     try: alt def {} {} {}",
+                entry.command,
+                suggestion.version,
+                suggestion.path.display()
+            );
+        }
+    }
+}
+
+pub fn run(format: &OutputFormat) -> anyhow::Result<()> {
+    let cwd = env::current_dir()?;
+    let use_files = use_file::load_all(use_file::find(&cwd))?;
+    let entries = check(&use_files)?;
+    let has_problems = entries.iter().any(|entry| entry.status != Status::Ok);
+
+    match format {
+        OutputFormat::Human => {
+            if use_files.is_empty() {
+                println!("No use file in {} or its parents.", cwd.display());
+            } else {
+                let paths = use_files
+                    .iter()
+                    .map(|(path, _)| path.display().to_string())
+                    .collect::<Vec<_>>();
+                println!("Versions from: {}", paths.join(", "));
+                print_entries(&entries);
+            }
+        }
+        OutputFormat::Json => {
+            let report = CheckReport {
+                files: use_files.iter().map(|(path, _)| path.as_path()).collect(),
+                commands: entries,
+            };
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report)
+                    .context("failed to serialize check to JSON")?
+            );
+        }
+    }
+
+    if has_problems {
+        process::exit(PROBLEMS_EXIT_CODE);
+    }
+
+    Ok(())
+}
@@ -47,10 +47,71 @@ pub fn make_app() -> Command {
         .subcommand(
             Command::new("scan")
                 .about("Scan for different versions of the given command")
+                .after_help(
+                    "EXAMPLES:
+    alt scan node                         Scan for versions of node
+    alt scan                              Scan for versions of every command
+    alt scan --all                        Scan for versions of every command
+    alt scan node --yes --filter '^18'    Add every version 18 of node
+    alt scan --dry-run --format json      List new versions as JSON
+    alt scan --update                     Add new versions & forget uninstalled ones
+
+EXIT STATUS:
+    3 when no versions were found (except with --update)",
+                )
                 .arg(
                     Arg::new("command")
-                        .required(true)
-                        .help("Command to scan for"),
+                        .help("Command to scan for (optional, defaults to all commands)"),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("command")
+                        .help("Scan for versions of every command"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Add every new version without prompting"),
+                )
+                .arg(
+                    Arg::new("filter")
+                        .long("filter")
+                        .value_name("regex")
+                        .help("Only keep versions whose name matches the given regex"),
+                )
+                .arg(
+                    Arg::new("dry_run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("yes")
+                        .help("Print the new versions instead of adding them"),
+                )
+                .arg(
+                    Arg::new("probe")
+                        .long("probe")
+                        .action(ArgAction::SetTrue)
+                        .help("Run the versions found to name them after their precise version"),
+                )
+                .arg(
+                    Arg::new("update")
+                        .long("update")
+                        .action(ArgAction::SetTrue)
+                        .help(
+                            "Add new versions, remove uninstalled versions and re-point moved \
+                            versions after showing the changes",
+                        ),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(PossibleValuesParser::new(["human", "json"]))
+                        .default_value("human")
+                        .requires("dry_run")
+                        .help("Output format of --dry-run"),
                 ),
         )
         .subcommand(
@@ -60,14 +121,28 @@ pub fn make_app() -> Command {
                     "EXAMPLES:
     alt use node 8        Use version 8 of node
     alt use node          Prompt for a version of node to use
-    alt use node system   Use the system version of node",
+    alt use node system   Use the system version of node
+    alt use --shared node 18
+                          Use version 18 of node for everyone working on the
+                          project
+
+FILES:
+    alt use writes your personal .alt.toml file. With --shared, it writes the
+    alt.toml file which is meant to be committed. When both are in the same
+    directory, .alt.toml overrides alt.toml.",
                 )
                 .arg(
                     Arg::new("command")
                         .required(true)
                         .help("Command to switch the version of"),
                 )
-                .arg(Arg::new("version").help("Version to use (optional)")),
+                .arg(Arg::new("version").help("Version to use (optional)"))
+                .arg(
+                    Arg::new("shared")
+                        .long("shared")
+                        .action(ArgAction::SetTrue)
+                        .help("Write the shared alt.toml file instead of .alt.toml"),
+                ),
         )
         .subcommand(Command::new("show").about("Print commands and their versions"))
         .subcommand(
@@ -89,6 +164,42 @@ pub fn make_app() -> Command {
                         .help("Path to the executable for the version"),
                 ),
         )
+        .subcommand(
+            Command::new("check")
+                .about("Check that the versions asked for in the current directory are available")
+                .after_help(
+                    "Reads the use files that apply to the current directory and checks that
+every version they ask for is defined and can be run. Versions found by alt
+scan are suggested for the ones that are missing.
+
+EXAMPLES:
+    alt check                  Check the versions of the current project
+    alt check --format json    Print the result as JSON
+
+EXIT STATUS:
+    4 when a version is missing or can't be run",
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(PossibleValuesParser::new(["human", "json"]))
+                        .default_value("human")
+                        .help("Output format"),
+                ),
+        )
+        .subcommand(
+            Command::new("allow")
+                .about("Trust the versions defined by the use files of the current directory")
+                .after_help(
+                    "Versions defined in a use file (under [defs]) are ignored until the
+file is trusted. Changing the file revokes the trust.",
+                ),
+        )
+        .subcommand(
+            Command::new("deny").about(
+                "Stop trusting the versions defined by the use files of the current directory",
+            ),
+        )
         .subcommand(
             Command::new("doctor")
                 .about("Checks if alt is setup correctly. Helps debug problems.")
@@ -1,15 +1,179 @@
 use crate::command_version::CommandVersionRegistry;
-use crate::use_file;
+use crate::config::OnUseFileError;
+use crate::environment::{load_command_version_registry, load_config, load_trust_store};
+use crate::use_file::{self, UseFile};
+use crate::version_req::VersionReq;
 use std::env;
 use std::fs;
 use std::path::*;
+use std::process;
 
-pub fn find_selected_version(command: &str) -> Option<String> {
-    let file = use_file::find(&env::current_dir().unwrap()).and_then(|path| use_file::load(&path));
+/// Find the use files that apply to the current directory when running
+/// `command`, in order of precedence. Use files that can't be loaded are
+/// reported and handled according to the `use_file.on_error` setting.
+pub fn find_use_files(command: &str) -> Vec<(PathBuf, UseFile)> {
+    let mut start = env::current_dir().unwrap();
+    loop {
+        let paths = use_file::find(&start);
+        let Some(dir) = paths.first().and_then(|path| path.parent()) else {
+            return vec![];
+        };
+        let parent = dir.parent().map(Path::to_owned);
 
-    file.as_ref()
-        .and_then(|file| file.get(command))
-        .map(|version| version.to_owned())
+        let err = match use_file::load_all(paths) {
+            Ok(files) => return files,
+            Err(err) => err,
+        };
+
+        // Shims run for every command, we can't fail with a backtrace here.
+        match on_error() {
+            OnUseFileError::System => {
+                eprintln!(
+                    "alt: {err}. Using the system {command}, fix the file or run `alt doctor`."
+                );
+                return vec![];
+            }
+            OnUseFileError::Parent => {
+                eprintln!("alt: {err}. Ignoring it, fix the file or run `alt doctor`.");
+                match parent {
+                    Some(parent) => start = parent,
+                    None => return vec![],
+                }
+            }
+            OnUseFileError::Fail => {
+                eprintln!("alt: {err}. Not running {command}, fix the file or run `alt doctor`.");
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// What to do when a file can't be loaded. The config is only loaded when
+/// needed to keep shims fast.
+fn on_error() -> OnUseFileError {
+    load_config()
+        .map(|config| config.use_file.on_error)
+        .unwrap_or_default()
+}
+
+/// Load the registry for a shim of `command`. When the definitions can't be
+/// loaded, the problem is reported on one line and `None` is returned to fall
+/// back on the system version, following the `use_file.on_error` setting.
+pub fn load_shim_registry(
+    use_files: &[(PathBuf, UseFile)],
+    command: &str,
+) -> Option<CommandVersionRegistry> {
+    let err = match load_registry(use_files) {
+        Ok(registry) => return Some(registry),
+        Err(err) => err,
+    };
+
+    // There is no parent to fall back on for definitions
+    match on_error() {
+        OnUseFileError::System | OnUseFileError::Parent => {
+            eprintln!("alt: {err:#}. Using the system {command}, fix the file.");
+            None
+        }
+        OnUseFileError::Fail => {
+            eprintln!("alt: {err:#}. Not running {command}, fix the file.");
+            process::exit(1);
+        }
+    }
+}
+
+/// Load the command version registry along with the versions defined by the
+/// use files
+pub fn load_registry(use_files: &[(PathBuf, UseFile)]) -> anyhow::Result<CommandVersionRegistry> {
+    let mut registry = load_command_version_registry()?;
+    add_all_project_defs(&mut registry, use_files)?;
+    Ok(registry)
+}
+
+/// Add the versions defined by use files given in order of precedence
+pub fn add_all_project_defs(
+    registry: &mut CommandVersionRegistry,
+    use_files: &[(PathBuf, UseFile)],
+) -> anyhow::Result<()> {
+    // Later layers override earlier ones
+    for (path, file) in use_files.iter().rev() {
+        add_project_defs(registry, path, file)?;
+    }
+    Ok(())
+}
+
+/// The use files ask for constraints that none of the defined versions meet
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("no version of {command} matches `{requested}`")]
+pub struct NoMatchingVersion {
+    pub command: String,
+    pub requested: String,
+}
+
+/// Resolve the version asked for `command` by the use files to the name of a
+/// version. Constraints (`>=3.11`) pick the highest version that matches.
+/// Names are returned as is even when they aren't defined. `None` means the
+/// system version.
+pub fn find_selected_version(
+    registry: &CommandVersionRegistry,
+    use_files: &[(PathBuf, UseFile)],
+    command: &str,
+) -> Result<Option<String>, NoMatchingVersion> {
+    let Some(requested) = use_file::requested_version(use_files, command) else {
+        return Ok(None);
+    };
+    resolve_version(registry, command, requested)
+}
+
+/// Resolve a version asked for `command` (a name or constraints) to the name
+/// of a version. `None` means the system version.
+pub fn resolve_version(
+    registry: &CommandVersionRegistry,
+    command: &str,
+    requested: &str,
+) -> Result<Option<String>, NoMatchingVersion> {
+    if requested == use_file::SYSTEM_VERSION {
+        return Ok(None);
+    }
+
+    let versions = registry
+        .entries()
+        .filter(|(version, entry)| version.command_name == command && !entry.disabled)
+        .map(|(version, _)| version.version_name)
+        .collect::<Vec<_>>();
+    let req = VersionReq::parse(requested);
+    match req.select(versions.iter().map(String::as_str)) {
+        Some(selected) => Ok(Some(selected.to_owned())),
+        None if matches!(req, VersionReq::Name(_)) => Ok(Some(requested.to_owned())),
+        None => Err(NoMatchingVersion {
+            command: command.to_owned(),
+            requested: requested.to_owned(),
+        }),
+    }
+}
+
+/// Add the versions defined by the use file at `path` to the registry. Since
+/// they can run anything, they are skipped until the user trusts the file with
+/// `alt allow`.
+pub fn add_project_defs(
+    registry: &mut CommandVersionRegistry,
+    path: &Path,
+    file: &UseFile,
+) -> anyhow::Result<()> {
+    // The trust store is only loaded when needed to keep shims fast
+    if file.defs.is_empty() {
+        return Ok(());
+    }
+
+    if load_trust_store()?.is_trusted(path, &file.digest) {
+        registry.add_project_layer(path, file.resolved_defs(path));
+    } else {
+        eprintln!(
+            "alt: ignoring the versions defined in {}, run `alt allow` to trust them.",
+            path.display()
+        );
+    }
+
+    Ok(())
 }
 
 pub fn find_system_bin(command: &str) -> Option<PathBuf> {
@@ -24,13 +188,30 @@ pub fn find_system_bin(command: &str) -> Option<PathBuf> {
 }
 
 pub fn find_selected_binary(
+    command_version_registry: &CommandVersionRegistry,
+    use_files: &[(PathBuf, UseFile)],
+    command_name: &str,
+) -> Result<Option<PathBuf>, NoMatchingVersion> {
+    let version = find_selected_version(command_version_registry, use_files, command_name)?;
+    Ok(find_binary(
+        command_version_registry,
+        command_name,
+        version.as_deref(),
+    ))
+}
+
+/// Find the binary for a version of a command. No version means the system
+/// version.
+pub fn find_binary(
     command_version_registry: &CommandVersionRegistry,
     command_name: &str,
+    version: Option<&str>,
 ) -> Option<PathBuf> {
-    match find_selected_version(command_name) {
+    match version {
         Some(version) => command_version_registry
-            .get(command_name, &version)
-            .map(|v| v.path),
+            .get_entry(command_name, version)
+            .filter(|entry| !entry.disabled)
+            .map(|entry| entry.path.clone()),
         None => find_system_bin(command_name),
     }
 }
@@ -1,9 +1,20 @@
+use crate::parse_error::ParseError;
+use crate::safe_file;
+use crate::version_order;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
     fmt::Display,
     fs, io,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
+use toml::value::{Date, Datetime};
+
+/// Version of the format of the definitions file. The first format (a table
+/// of paths per command) had no version.
+const FORMAT_VERSION: i64 = 2;
 
 #[derive(thiserror::Error, Debug)]
 pub enum SaveError {
@@ -15,8 +26,10 @@ pub enum SaveError {
 
 #[derive(thiserror::Error, Debug)]
 pub enum LoadError {
-    #[error("failed to deserialize CommandVersionRegistry state from TOML")]
-    TomlError(#[from] toml::de::Error),
+    #[error("invalid definitions file {0}")]
+    ParseError(#[from] ParseError),
+    #[error("unsupported definitions format version {0}, try upgrading alt")]
+    UnsupportedFormatVersion(String),
     #[error(transparent)]
     IoError(#[from] io::Error),
 }
@@ -29,7 +42,7 @@ pub enum AddError {
     InvalidPathNotAbsolute(CommandVersion),
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct CommandVersion {
     pub command_name: String,
     pub version_name: String,
@@ -46,6 +59,23 @@ impl CommandVersion {
     }
 }
 
+/// Command versions are grouped by command and then sorted by version (see
+/// [`version_order::compare`]).
+impl Ord for CommandVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.command_name
+            .cmp(&other.command_name)
+            .then_with(|| version_order::compare(&self.version_name, &other.version_name))
+            .then_with(|| self.path.cmp(&other.path))
+    }
+}
+
+impl PartialOrd for CommandVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Display for CommandVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -58,22 +88,190 @@ impl Display for CommandVersion {
     }
 }
 
-type RegistryState = HashMap<String, HashMap<String, PathBuf>>;
+/// Everything we know about a version of a command. Only the path is required.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct VersionEntry {
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Scanner that found the version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<Datetime>,
+    /// Version printed by the binary (see `alt scan --probe`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probed_version: Option<String>,
+    /// Environment variables set when running the version
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Disabled versions are kept around but never run
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub disabled: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl From<PathBuf> for VersionEntry {
+    fn from(path: PathBuf) -> Self {
+        VersionEntry {
+            path,
+            ..VersionEntry::default()
+        }
+    }
+}
+
+type RegistryState = HashMap<String, HashMap<String, VersionEntry>>;
+
+/// First format of the definitions file
+type RegistryStateV1 = HashMap<String, HashMap<String, PathBuf>>;
+
+#[derive(Serialize, Deserialize, Default)]
+struct RegistryFile {
+    format_version: i64,
+    /// Other definitions files to load before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<PathBuf>,
+    #[serde(default)]
+    commands: RegistryState,
+}
+
+/// Where a version was defined. Versions defined in later layers override the
+/// ones defined in earlier layers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    /// Definitions shared by every user of the machine (`/etc/alt/defs.toml`)
+    System,
+    /// Definitions file listed in the `include` key of another file
+    Include(PathBuf),
+    /// The user's own definitions. This is the only layer that gets saved.
+    User,
+    /// Definitions from the use file of a project. These override every other
+    /// layer.
+    Project(PathBuf),
+}
+
+impl Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layer::System => write!(f, "system"),
+            Layer::Include(path) => write!(f, "{}", path.display()),
+            Layer::User => write!(f, "user"),
+            Layer::Project(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+type InheritedState = HashMap<String, HashMap<String, (Layer, VersionEntry)>>;
+
+/// Convert days since the unix epoch to a civil date.
+/// See: https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> Date {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    Date {
+        year: year as u16,
+        month: month as u8,
+        day: day as u8,
+    }
+}
+
+fn today() -> Datetime {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    Datetime {
+        date: Some(civil_from_days((secs / 86400) as i64)),
+        time: None,
+        offset: None,
+    }
+}
+
+fn parse_file(path: &Path, contents: &str) -> Result<RegistryFile, LoadError> {
+    let parse_error = |err| ParseError::new(path, contents, &err);
+
+    // The table is only used to find the format. Dates don't survive being
+    // deserialized from a table so the contents are parsed again.
+    let table: toml::Table = toml::from_str(contents).map_err(parse_error)?;
+
+    match table.get("format_version") {
+        None => {
+            let state: RegistryStateV1 = toml::from_str(contents).map_err(parse_error)?;
+            let commands = state
+                .into_iter()
+                .map(|(command, versions)| {
+                    let versions = versions
+                        .into_iter()
+                        .map(|(version, path)| (version, VersionEntry::from(path)))
+                        .collect();
+                    (command, versions)
+                })
+                .collect();
+
+            Ok(RegistryFile {
+                format_version: FORMAT_VERSION,
+                commands,
+                ..RegistryFile::default()
+            })
+        }
+        Some(toml::Value::Integer(FORMAT_VERSION)) => {
+            Ok(toml::from_str(contents).map_err(parse_error)?)
+        }
+        Some(version) => Err(LoadError::UnsupportedFormatVersion(version.to_string())),
+    }
+}
+
+/// Load a definitions file that isn't saved. A missing file is the same as an
+/// empty one.
+fn load_file_or_default(path: &Path) -> Result<RegistryFile, LoadError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse_file(path, &contents),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(RegistryFile::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Included paths are relative to the file that includes them.
+fn resolve_includes(file_path: &Path, include: &[PathBuf]) -> Vec<PathBuf> {
+    let dir = file_path.parent().unwrap_or(Path::new("/"));
+    include.iter().map(|path| dir.join(path)).collect()
+}
 
 #[derive(Debug)]
 pub struct CommandVersionRegistry {
     path: PathBuf,
+    /// The user layer
     state: RegistryState,
+    include: Vec<PathBuf>,
+    /// Versions defined in the layers below the user layer
+    inherited: InheritedState,
+    /// Versions defined by the project, above the user layer
+    project: InheritedState,
 }
 
 impl CommandVersionRegistry {
+    /// Load the definitions file. Files in the first format are migrated.
     pub fn load(path: &Path) -> Result<Self, LoadError> {
         let contents = fs::read_to_string(path)?;
-        let state: RegistryState = toml::from_str(&contents)?;
+        let file = parse_file(path, &contents)?;
 
         Ok(CommandVersionRegistry {
             path: path.to_owned(),
-            state,
+            state: file.commands,
+            include: file.include,
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         })
     }
 
@@ -81,6 +279,54 @@ impl CommandVersionRegistry {
         CommandVersionRegistry {
             path: path.to_owned(),
             state: RegistryState::default(),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
+        }
+    }
+
+    /// Load the user's definitions file (`path`) on top of the system
+    /// definitions file and of the files that either of them include. Only
+    /// the user's definitions get saved.
+    pub fn load_layered(system_path: &Path, path: &Path) -> Result<Self, LoadError> {
+        let mut registry = Self::load_or_new(path)?;
+        let system = load_file_or_default(system_path)?;
+
+        let includes = resolve_includes(system_path, &system.include)
+            .into_iter()
+            .chain(resolve_includes(path, &registry.include));
+
+        let mut layers = vec![(Layer::System, system.commands)];
+        for include in includes {
+            let file = load_file_or_default(&include)?;
+            layers.push((Layer::Include(include), file.commands));
+        }
+
+        for (layer, state) in layers {
+            for (command, versions) in state {
+                let inherited_versions = registry.inherited.entry(command).or_default();
+                for (version, entry) in versions {
+                    inherited_versions.insert(version, (layer.clone(), entry));
+                }
+            }
+        }
+
+        Ok(registry)
+    }
+
+    /// Add the versions defined by the use file at `path`. They are never
+    /// saved.
+    pub fn add_project_layer(
+        &mut self,
+        path: &Path,
+        defs: BTreeMap<String, BTreeMap<String, PathBuf>>,
+    ) {
+        for (command, versions) in defs {
+            let project_versions = self.project.entry(command).or_default();
+            for (version, bin) in versions {
+                let layer = Layer::Project(path.to_owned());
+                project_versions.insert(version, (layer, VersionEntry::from(bin)));
+            }
         }
     }
 
@@ -99,27 +345,80 @@ impl CommandVersionRegistry {
             fs::create_dir_all(parent)?;
         }
 
-        let toml = toml::to_string_pretty(&self.state)?;
-        fs::write(&self.path, toml)?;
+        // Always saved in the latest format
+        let toml = toml::to_string_pretty(&RegistryFile {
+            format_version: FORMAT_VERSION,
+            include: self.include.clone(),
+            commands: self.state.clone(),
+        })?;
+        safe_file::write_atomic(&self.path, toml)?;
         Ok(())
     }
 
     pub fn get(&self, command: &str, version: &str) -> Option<CommandVersion> {
-        let path = self.state.get(command)?.get(version).cloned()?;
-        Some(CommandVersion::new(command, version, &path))
+        let entry = self.get_entry(command, version)?;
+        Some(CommandVersion::new(command, version, &entry.path))
+    }
+
+    pub fn get_entry(&self, command: &str, version: &str) -> Option<&VersionEntry> {
+        if let Some((_, entry)) = self.project.get(command).and_then(|v| v.get(version)) {
+            return Some(entry);
+        }
+
+        let entry = self
+            .state
+            .get(command)
+            .and_then(|versions| versions.get(version));
+        entry.or_else(|| {
+            let (_, entry) = self.inherited.get(command)?.get(version)?;
+            Some(entry)
+        })
+    }
+
+    /// Find the layer that defines a version
+    pub fn layer(&self, command: &str, version: &str) -> Option<&Layer> {
+        if let Some((layer, _)) = self.project.get(command).and_then(|v| v.get(version)) {
+            return Some(layer);
+        }
+
+        if self
+            .state
+            .get(command)
+            .is_some_and(|versions| versions.contains_key(version))
+        {
+            return Some(&Layer::User);
+        }
+
+        let (layer, _) = self.inherited.get(command)?.get(version)?;
+        Some(layer)
     }
 
-    pub fn add(&mut self, command_version: CommandVersion) -> Result<(), AddError> {
+    /// Add a version or point an existing version to a new path. The metadata
+    /// of an existing version is kept except for the probed version which
+    /// belongs to the old path. The entry is returned so that metadata can be
+    /// filled in.
+    pub fn add(&mut self, command_version: CommandVersion) -> Result<&mut VersionEntry, AddError> {
         if !command_version.path.is_absolute() {
             return Err(AddError::InvalidPathNotAbsolute(command_version));
         }
 
         let command_entry = self.state.entry(command_version.command_name).or_default();
-        command_entry.insert(command_version.version_name, command_version.path);
+        let entry = command_entry
+            .entry(command_version.version_name)
+            .or_insert_with(|| VersionEntry {
+                added: Some(today()),
+                ..VersionEntry::default()
+            });
+        if entry.path != command_version.path {
+            entry.path = command_version.path;
+            entry.probed_version = None;
+        }
 
-        Ok(())
+        Ok(entry)
     }
 
+    /// Remove a version from the user layer. Versions defined in other layers
+    /// can't be removed.
     pub fn remove(&mut self, command: &str, version: &str) {
         let versions = self.state.get_mut(command);
         if let Some(versions) = versions {
@@ -131,19 +430,61 @@ impl CommandVersionRegistry {
     }
 
     pub fn iter(&self) -> impl Iterator<Item = CommandVersion> + '_ {
-        self.state.iter().flat_map(|(command_name, versions)| {
+        self.entries().map(|(command_version, _)| command_version)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (CommandVersion, &VersionEntry)> + '_ {
+        let project = self.project.iter().flat_map(|(command_name, versions)| {
             versions
                 .iter()
-                .map(|(version_name, path)| CommandVersion::new(command_name, version_name, path))
-        })
+                .map(move |(version_name, (_, entry))| (command_name, version_name, entry))
+        });
+
+        let user = self.state.iter().flat_map(|(command_name, versions)| {
+            versions
+                .iter()
+                .filter(|(version_name, _)| {
+                    self.layer(command_name, version_name) == Some(&Layer::User)
+                })
+                .map(move |(version_name, entry)| (command_name, version_name, entry))
+        });
+
+        let inherited = self.inherited.iter().flat_map(|(command_name, versions)| {
+            versions
+                .iter()
+                .filter(|(version_name, _)| {
+                    matches!(
+                        self.layer(command_name, version_name),
+                        Some(Layer::System | Layer::Include(_))
+                    )
+                })
+                .map(move |(version_name, (_, entry))| (command_name, version_name, entry))
+        });
+
+        project
+            .chain(user)
+            .chain(inherited)
+            .map(|(command_name, version_name, entry)| {
+                (
+                    CommandVersion::new(command_name, version_name, &entry.path),
+                    entry,
+                )
+            })
     }
 
     pub fn command_names(&self) -> impl Iterator<Item = String> + '_ {
-        self.state.keys().cloned()
+        let user = self
+            .state
+            .keys()
+            .filter(|command| !self.project.contains_key(*command));
+        let inherited = self.inherited.keys().filter(|command| {
+            !self.project.contains_key(*command) && !self.state.contains_key(*command)
+        });
+        self.project.keys().chain(user).chain(inherited).cloned()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.state.is_empty()
+        self.state.is_empty() && self.inherited.is_empty() && self.project.is_empty()
     }
 }
 
@@ -155,6 +496,20 @@ mod tests {
 
     type TestResult = Result<(), Box<dyn Error>>;
 
+    /// Registry state without the metadata
+    fn paths(state: &RegistryState) -> RegistryStateV1 {
+        state
+            .iter()
+            .map(|(command, versions)| {
+                let versions = versions
+                    .iter()
+                    .map(|(version, entry)| (version.clone(), entry.path.clone()))
+                    .collect();
+                (command.clone(), versions)
+            })
+            .collect()
+    }
+
     #[test]
     fn default_is_empty() {
         let registry = CommandVersionRegistry::new(Path::new("not-important"));
@@ -191,8 +546,11 @@ mod tests {
             HashMap::from([(
                 "the-command".to_string(),
                 HashMap::from([
-                    ("42".to_string(), PathBuf::from("/path/to/something")),
-                    ("43".to_string(), PathBuf::from("/path/to/something-else")),
+                    ("42".to_string(), PathBuf::from("/path/to/something").into()),
+                    (
+                        "43".to_string(),
+                        PathBuf::from("/path/to/something-else").into()
+                    ),
                 ])
             )])
         );
@@ -239,10 +597,19 @@ mod tests {
             state: HashMap::from([(
                 "the-command".to_string(),
                 HashMap::from([
-                    ("42".to_string(), PathBuf::from("path/to/the-command-v42")),
-                    ("43".to_string(), PathBuf::from("path/to/the-command-v43")),
+                    (
+                        "42".to_string(),
+                        PathBuf::from("path/to/the-command-v42").into(),
+                    ),
+                    (
+                        "43".to_string(),
+                        PathBuf::from("path/to/the-command-v43").into(),
+                    ),
                 ]),
             )]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         assert!(!file_path.exists());
@@ -266,10 +633,19 @@ mod tests {
             state: HashMap::from([(
                 "the-command".to_string(),
                 HashMap::from([
-                    ("42".to_string(), PathBuf::from("path/to/the-command-v42")),
-                    ("43".to_string(), PathBuf::from("path/to/the-command-v43")),
+                    (
+                        "42".to_string(),
+                        PathBuf::from("path/to/the-command-v42").into(),
+                    ),
+                    (
+                        "43".to_string(),
+                        PathBuf::from("path/to/the-command-v43").into(),
+                    ),
                 ]),
             )]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         assert!(!parent_dir.exists());
@@ -294,18 +670,140 @@ mod tests {
                 (
                     "the-command".to_string(),
                     HashMap::from([
-                        ("42".to_string(), PathBuf::from("path/to/the-command-v42")),
-                        ("43".to_string(), PathBuf::from("path/to/the-command-v43")),
+                        (
+                            "42".to_string(),
+                            PathBuf::from("path/to/the-command-v42").into(),
+                        ),
+                        (
+                            "43".to_string(),
+                            PathBuf::from("path/to/the-command-v43").into(),
+                        ),
                     ]),
                 ),
                 (
                     "node".to_string(),
                     HashMap::from([
-                        ("16".to_string(), PathBuf::from("path/to/node-16")),
-                        ("18".to_string(), PathBuf::from("path/to/node-18")),
+                        ("16".to_string(), PathBuf::from("path/to/node-16").into()),
+                        ("18".to_string(), PathBuf::from("path/to/node-18").into()),
                     ]),
                 ),
             ]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
+        };
+
+        new_registry.save()?;
+
+        let loaded_registry = CommandVersionRegistry::load(tempfile.path())?;
+
+        assert_eq!(new_registry.state, loaded_registry.state);
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_migrates_first_format() -> TestResult {
+        let tempfile = NamedTempFile::new()?;
+        fs::write(
+            tempfile.path(),
+            concat!(
+                "[node]\n",
+                "\"16\" = \"/path/to/node-16\"\n",
+                "\"18\" = \"/path/to/node-18\"\n",
+            ),
+        )?;
+
+        let registry = CommandVersionRegistry::load(tempfile.path())?;
+        registry.save()?;
+
+        let contents = fs::read_to_string(tempfile.path())?;
+        assert!(contents.starts_with("format_version = 2\n"));
+
+        let reloaded_registry = CommandVersionRegistry::load(tempfile.path())?;
+        assert_eq!(reloaded_registry.state, registry.state);
+        assert_eq!(
+            paths(&reloaded_registry.state),
+            HashMap::from([(
+                "node".to_string(),
+                HashMap::from([
+                    ("16".to_string(), PathBuf::from("/path/to/node-16")),
+                    ("18".to_string(), PathBuf::from("/path/to/node-18")),
+                ])
+            )])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_reads_metadata() -> TestResult {
+        let tempfile = NamedTempFile::new()?;
+        fs::write(
+            tempfile.path(),
+            concat!(
+                "format_version = 2\n",
+                "\n",
+                "[commands.python.\"3.11\"]\n",
+                "path = \"/usr/bin/python3.11\"\n",
+                "description = \"system python\"\n",
+                "source = \"path_suffix\"\n",
+                "added = 2024-01-02\n",
+                "probed_version = \"3.11.7\"\n",
+                "disabled = true\n",
+                "\n",
+                "[commands.python.\"3.11\".env]\n",
+                "PYTHONNOUSERSITE = \"1\"\n",
+            ),
+        )?;
+
+        let registry = CommandVersionRegistry::load(tempfile.path())?;
+
+        assert_eq!(
+            registry.get_entry("python", "3.11"),
+            Some(&VersionEntry {
+                path: PathBuf::from("/usr/bin/python3.11"),
+                description: Some("system python".to_string()),
+                source: Some("path_suffix".to_string()),
+                added: Some("2024-01-02".parse()?),
+                probed_version: Some("3.11.7".to_string()),
+                env: BTreeMap::from([("PYTHONNOUSERSITE".to_string(), "1".to_string())]),
+                disabled: true,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_and_load_preserves_metadata() -> TestResult {
+        let tempfile = NamedTempFile::new()?;
+        let new_registry = CommandVersionRegistry {
+            path: tempfile.path().to_owned(),
+            state: HashMap::from([(
+                "node".to_string(),
+                HashMap::from([
+                    (
+                        "18".to_string(),
+                        VersionEntry {
+                            path: PathBuf::from("/path/to/node-18"),
+                            description: Some("LTS".to_string()),
+                            source: Some("node".to_string()),
+                            added: Some("2024-01-02".parse()?),
+                            probed_version: Some("18.19.0".to_string()),
+                            env: BTreeMap::from([(
+                                "NODE_OPTIONS".to_string(),
+                                "--max-old-space-size=4096".to_string(),
+                            )]),
+                            disabled: true,
+                        },
+                    ),
+                    ("20".to_string(), PathBuf::from("/path/to/node-20").into()),
+                ]),
+            )]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         new_registry.save()?;
@@ -317,6 +815,228 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn load_fails_on_unknown_format_version() -> TestResult {
+        let tempfile = NamedTempFile::new()?;
+        fs::write(tempfile.path(), "format_version = 3\n")?;
+
+        let res = CommandVersionRegistry::load(tempfile.path());
+        assert!(matches!(res, Err(LoadError::UnsupportedFormatVersion(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_fails_with_location_of_invalid_toml() -> TestResult {
+        let tempfile = NamedTempFile::new()?;
+        fs::write(
+            tempfile.path(),
+            "format_version = 2\n\n[commands.node.18]\npath = /bin/node\n",
+        )?;
+
+        match CommandVersionRegistry::load(tempfile.path()) {
+            Err(LoadError::ParseError(err)) => {
+                assert_eq!(err.path, tempfile.path());
+                assert_eq!((err.line, err.column), (4, 8));
+            }
+            res => panic!("expected a parse error, got {res:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_keeps_metadata_when_changing_path() -> anyhow::Result<()> {
+        let mut registry = CommandVersionRegistry::new(Path::new("not-important"));
+
+        let entry = registry.add(CommandVersion::new("node", "18", Path::new("/old/node")))?;
+        entry.description = Some("LTS".to_string());
+        entry.probed_version = Some("18.19.0".to_string());
+
+        registry.add(CommandVersion::new("node", "18", Path::new("/new/node")))?;
+
+        let entry = registry.get_entry("node", "18").unwrap();
+        assert_eq!(entry.path, PathBuf::from("/new/node"));
+        assert_eq!(entry.description.as_deref(), Some("LTS"));
+        assert_eq!(entry.probed_version, None);
+        assert!(entry.added.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn civil_from_days_converts_dates() {
+        assert_eq!(
+            civil_from_days(0),
+            Date {
+                year: 1970,
+                month: 1,
+                day: 1
+            }
+        );
+        assert_eq!(
+            civil_from_days(19722),
+            Date {
+                year: 2023,
+                month: 12,
+                day: 31
+            }
+        );
+        assert_eq!(
+            civil_from_days(19782),
+            Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            }
+        );
+    }
+
+    #[test]
+    fn load_layered_merges_layers_in_order() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let system_path = workdir.path().join("system.toml");
+        let team_path = workdir.path().join("team.toml");
+        let user_path = workdir.path().join("user.toml");
+        fs::write(
+            &system_path,
+            concat!(
+                "[node]\n",
+                "\"16\" = \"/opt/node-16\"\n",
+                "\"18\" = \"/opt/node-18\"\n",
+                "\"20\" = \"/opt/node-20\"\n",
+            ),
+        )?;
+        fs::write(
+            &team_path,
+            concat!(
+                "format_version = 2\n",
+                "[commands.node.\"18\"]\n",
+                "path = \"/team/node-18\"\n",
+                "[commands.node.\"20\"]\n",
+                "path = \"/team/node-20\"\n",
+            ),
+        )?;
+        fs::write(
+            &user_path,
+            concat!(
+                "format_version = 2\n",
+                "include = [\"team.toml\"]\n",
+                "[commands.node.\"20\"]\n",
+                "path = \"/home/me/node-20\"\n",
+            ),
+        )?;
+
+        let registry = CommandVersionRegistry::load_layered(&system_path, &user_path)?;
+
+        let mut res = registry.iter().collect::<Vec<_>>();
+        res.sort();
+        assert_eq!(
+            res,
+            vec![
+                CommandVersion::new("node", "16", Path::new("/opt/node-16")),
+                CommandVersion::new("node", "18", Path::new("/team/node-18")),
+                CommandVersion::new("node", "20", Path::new("/home/me/node-20")),
+            ]
+        );
+        assert_eq!(registry.layer("node", "16"), Some(&Layer::System));
+        assert_eq!(
+            registry.layer("node", "18"),
+            Some(&Layer::Include(team_path.clone()))
+        );
+        assert_eq!(registry.layer("node", "20"), Some(&Layer::User));
+        assert_eq!(registry.command_names().collect::<Vec<_>>(), vec!["node"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn project_layer_overrides_other_layers() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let user_path = workdir.path().join("user.toml");
+        let project_path = workdir.path().join(".alt.toml");
+        fs::write(
+            &user_path,
+            concat!(
+                "format_version = 2\n",
+                "[commands.protoc.\"3.21\"]\n",
+                "path = \"/usr/bin/protoc\"\n",
+                "[commands.protoc.\"3.20\"]\n",
+                "path = \"/opt/protoc-3.20\"\n",
+            ),
+        )?;
+
+        let mut registry =
+            CommandVersionRegistry::load_layered(&workdir.path().join("system.toml"), &user_path)?;
+        registry.add_project_layer(
+            &project_path,
+            BTreeMap::from([(
+                "protoc".to_string(),
+                BTreeMap::from([("3.21".to_string(), PathBuf::from("/project/protoc"))]),
+            )]),
+        );
+
+        let mut res = registry.iter().collect::<Vec<_>>();
+        res.sort();
+        assert_eq!(
+            res,
+            vec![
+                CommandVersion::new("protoc", "3.20", Path::new("/opt/protoc-3.20")),
+                CommandVersion::new("protoc", "3.21", Path::new("/project/protoc")),
+            ]
+        );
+        assert_eq!(
+            registry.layer("protoc", "3.21"),
+            Some(&Layer::Project(project_path))
+        );
+        assert_eq!(registry.command_names().collect::<Vec<_>>(), vec!["protoc"]);
+
+        registry.save()?;
+        assert!(!fs::read_to_string(&user_path)?.contains("/project/protoc"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_only_writes_user_layer() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let system_path = workdir.path().join("system.toml");
+        let user_path = workdir.path().join("user.toml");
+        fs::write(&system_path, "[node]\n\"16\" = \"/opt/node-16\"\n")?;
+        fs::write(
+            &user_path,
+            "format_version = 2\ninclude = [\"/does/not/exist.toml\"]\n",
+        )?;
+
+        let mut registry = CommandVersionRegistry::load_layered(&system_path, &user_path)?;
+        registry.add(CommandVersion::new(
+            "node",
+            "18",
+            Path::new("/home/me/node-18"),
+        ))?;
+        registry.remove("node", "16");
+        registry.save()?;
+
+        let user_registry = CommandVersionRegistry::load(&user_path)?;
+        assert_eq!(
+            paths(&user_registry.state),
+            HashMap::from([(
+                "node".to_string(),
+                HashMap::from([("18".to_string(), PathBuf::from("/home/me/node-18"))])
+            )])
+        );
+        assert_eq!(
+            user_registry.include,
+            vec![PathBuf::from("/does/not/exist.toml")]
+        );
+        assert_eq!(
+            fs::read_to_string(&system_path)?,
+            "[node]\n\"16\" = \"/opt/node-16\"\n"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn add_creates_new_command() -> anyhow::Result<()> {
         let mut registry = CommandVersionRegistry::new(Path::new("not-important"));
@@ -329,7 +1049,7 @@ mod tests {
         ))?;
 
         assert_eq!(
-            registry.state,
+            paths(&registry.state),
             HashMap::from([(
                 "the-command".to_string(),
                 HashMap::from([("42".to_string(), PathBuf::from("/path/to/the-command-v42")),])
@@ -356,7 +1076,7 @@ mod tests {
             Path::new("/path/to/the-command-v43"),
         ))?;
         assert_eq!(
-            registry.state,
+            paths(&registry.state),
             HashMap::from([(
                 "the-command".to_string(),
                 HashMap::from([
@@ -395,8 +1115,11 @@ mod tests {
             path: PathBuf::from("not-important"),
             state: HashMap::from([(
                 "node".to_string(),
-                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18"))]),
+                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18").into())]),
             )]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         let res = registry.get("node", "18");
@@ -416,8 +1139,11 @@ mod tests {
             path: PathBuf::from("not-important"),
             state: HashMap::from([(
                 "node".to_string(),
-                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18"))]),
+                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18").into())]),
             )]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         let res = registry.get("node", "not-there");
@@ -430,8 +1156,11 @@ mod tests {
             path: PathBuf::from("not-important"),
             state: HashMap::from([(
                 "node".to_string(),
-                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18"))]),
+                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18").into())]),
             )]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         let res = registry.get("not-there", "not-there");
@@ -455,18 +1184,21 @@ mod tests {
                 (
                     "foo".to_string(),
                     HashMap::from([
-                        ("42".to_string(), PathBuf::from("path/to/foo-42")),
-                        ("43".to_string(), PathBuf::from("path/to/foo-43")),
+                        ("42".to_string(), PathBuf::from("path/to/foo-42").into()),
+                        ("43".to_string(), PathBuf::from("path/to/foo-43").into()),
                     ]),
                 ),
                 (
                     "node".to_string(),
                     HashMap::from([
-                        ("16".to_string(), PathBuf::from("path/to/node-16")),
-                        ("18".to_string(), PathBuf::from("path/to/node-18")),
+                        ("16".to_string(), PathBuf::from("path/to/node-16").into()),
+                        ("18".to_string(), PathBuf::from("path/to/node-18").into()),
                     ]),
                 ),
             ]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         registry.remove("foo", "42");
@@ -476,13 +1208,13 @@ mod tests {
             HashMap::from([
                 (
                     "foo".to_string(),
-                    HashMap::from([("43".to_string(), PathBuf::from("path/to/foo-43")),]),
+                    HashMap::from([("43".to_string(), PathBuf::from("path/to/foo-43").into()),]),
                 ),
                 (
                     "node".to_string(),
                     HashMap::from([
-                        ("16".to_string(), PathBuf::from("path/to/node-16")),
-                        ("18".to_string(), PathBuf::from("path/to/node-18")),
+                        ("16".to_string(), PathBuf::from("path/to/node-16").into()),
+                        ("18".to_string(), PathBuf::from("path/to/node-18").into()),
                     ]),
                 ),
             ])
@@ -496,16 +1228,19 @@ mod tests {
             state: HashMap::from([
                 (
                     "foo".to_string(),
-                    HashMap::from([("43".to_string(), PathBuf::from("path/to/foo-43"))]),
+                    HashMap::from([("43".to_string(), PathBuf::from("path/to/foo-43").into())]),
                 ),
                 (
                     "node".to_string(),
                     HashMap::from([
-                        ("16".to_string(), PathBuf::from("path/to/node-16")),
-                        ("18".to_string(), PathBuf::from("path/to/node-18")),
+                        ("16".to_string(), PathBuf::from("path/to/node-16").into()),
+                        ("18".to_string(), PathBuf::from("path/to/node-18").into()),
                     ]),
                 ),
             ]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         registry.remove("foo", "43");
@@ -515,8 +1250,8 @@ mod tests {
             HashMap::from([(
                 "node".to_string(),
                 HashMap::from([
-                    ("16".to_string(), PathBuf::from("path/to/node-16")),
-                    ("18".to_string(), PathBuf::from("path/to/node-18")),
+                    ("16".to_string(), PathBuf::from("path/to/node-16").into()),
+                    ("18".to_string(), PathBuf::from("path/to/node-18").into()),
                 ]),
             ),])
         );
@@ -537,18 +1272,27 @@ mod tests {
                 (
                     "the-command".to_string(),
                     HashMap::from([
-                        ("42".to_string(), PathBuf::from("path/to/the-command-v42")),
-                        ("43".to_string(), PathBuf::from("path/to/the-command-v43")),
+                        (
+                            "42".to_string(),
+                            PathBuf::from("path/to/the-command-v42").into(),
+                        ),
+                        (
+                            "43".to_string(),
+                            PathBuf::from("path/to/the-command-v43").into(),
+                        ),
                     ]),
                 ),
                 (
                     "node".to_string(),
                     HashMap::from([
-                        ("16".to_string(), PathBuf::from("path/to/node-16")),
-                        ("18".to_string(), PathBuf::from("path/to/node-18")),
+                        ("16".to_string(), PathBuf::from("path/to/node-16").into()),
+                        ("18".to_string(), PathBuf::from("path/to/node-18").into()),
                     ]),
                 ),
             ]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         let mut res = registry.iter().collect::<Vec<_>>();
@@ -582,18 +1326,27 @@ mod tests {
                 (
                     "the-command".to_string(),
                     HashMap::from([
-                        ("42".to_string(), PathBuf::from("path/to/the-command-v42")),
-                        ("43".to_string(), PathBuf::from("path/to/the-command-v43")),
+                        (
+                            "42".to_string(),
+                            PathBuf::from("path/to/the-command-v42").into(),
+                        ),
+                        (
+                            "43".to_string(),
+                            PathBuf::from("path/to/the-command-v43").into(),
+                        ),
                     ]),
                 ),
                 (
                     "node".to_string(),
                     HashMap::from([
-                        ("16".to_string(), PathBuf::from("path/to/node-16")),
-                        ("18".to_string(), PathBuf::from("path/to/node-18")),
+                        ("16".to_string(), PathBuf::from("path/to/node-16").into()),
+                        ("18".to_string(), PathBuf::from("path/to/node-18").into()),
                     ]),
                 ),
             ]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
 
         let mut res = registry.command_names().collect::<Vec<_>>();
@@ -614,8 +1367,11 @@ mod tests {
             path: PathBuf::from("not-important"),
             state: HashMap::from([(
                 "node".to_string(),
-                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18"))]),
+                HashMap::from([("18".to_string(), PathBuf::from("path/to/node-18").into())]),
             )]),
+            include: vec![],
+            inherited: InheritedState::default(),
+            project: InheritedState::default(),
         };
         assert!(!registry.is_empty())
     }
@@ -0,0 +1,138 @@
+use crate::parse_error::ParseError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use std::{fs, io, path::Path};
+
+const DEFAULT_PROBE_FLAGS: [&str; 3] = ["--version", "-V", "version"];
+const DEFAULT_PROBE_TIMEOUT_MS: u64 = 2000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadError {
+    #[error("invalid config file {0}")]
+    ParseError(#[from] ParseError),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// User configuration (`config.toml` in the alt home directory). Every
+/// setting is optional.
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub probe: ProbeConfig,
+    pub use_file: UseFileConfig,
+}
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct UseFileConfig {
+    /// What shims do when a use file can't be read
+    pub on_error: OnUseFileError,
+}
+
+#[derive(Deserialize, Default, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum OnUseFileError {
+    /// Run the system version of the command
+    #[default]
+    System,
+    /// Skip the broken file and use the next use file up the tree
+    Parent,
+    /// Don't run anything
+    Fail,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProbeConfig {
+    /// How long a binary gets to print its version before being killed
+    pub timeout_ms: u64,
+    /// Flags to try for specific commands (ex: `java = ["-version"]`). Each
+    /// flag is tried in order until one of them prints a version.
+    pub flags: HashMap<String, Vec<String>>,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        ProbeConfig {
+            timeout_ms: DEFAULT_PROBE_TIMEOUT_MS,
+            flags: HashMap::new(),
+        }
+    }
+}
+
+impl ProbeConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    pub fn flags_for(&self, command: &str) -> Vec<&str> {
+        match self.flags.get(command) {
+            Some(flags) => flags.iter().map(String::as_str).collect(),
+            None => DEFAULT_PROBE_FLAGS.to_vec(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load_or_default(path: &Path) -> Result<Self, LoadError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|err| ParseError::new(path, &contents, &err).into()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(
+            config.probe.flags_for("node"),
+            vec!["--version", "-V", "version"]
+        );
+        assert_eq!(config.probe.timeout(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn probe_flags_can_be_set_per_command() {
+        let config: Config = toml::from_str(
+            r#"
+            [probe]
+            timeout_ms = 500
+
+            [probe.flags]
+            java = ["-version"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.probe.flags_for("java"), vec!["-version"]);
+        assert_eq!(
+            config.probe.flags_for("node"),
+            vec!["--version", "-V", "version"]
+        );
+        assert_eq!(config.probe.timeout(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn use_file_error_policy() {
+        let config: Config = toml::from_str(
+            r#"
+            [use_file]
+            on_error = "parent"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.use_file.on_error, OnUseFileError::Parent);
+        assert_eq!(Config::default().use_file.on_error, OnUseFileError::System);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::command_version::CommandVersion;
-use crate::environment::load_command_version_registry;
+use crate::environment::{load_command_version_registry, lock_home};
 use crate::shim;
 use anyhow::Context;
 use std::env;
@@ -10,6 +10,7 @@ pub fn run(command: &str, version: &str, bin: &str) -> anyhow::Result<()> {
     let bin_path = fs::canonicalize(Path::new(bin))
         .with_context(|| format!("failed to resolve {bin} to an absolute path"))?;
 
+    let _lock = lock_home()?;
     let mut registry = load_command_version_registry()?;
     registry.add(CommandVersion::new(command, version, &bin_path))?;
     registry
@@ -0,0 +1,27 @@
+use crate::environment::{load_trust_store, lock_home, trust_file};
+use crate::use_file;
+use anyhow::{bail, Context};
+use std::env;
+
+pub fn run() -> anyhow::Result<()> {
+    let cwd = env::current_dir()?;
+    let paths = use_file::find(&cwd);
+    if paths.is_empty() {
+        bail!("no use file found in {} or its parents", cwd.display());
+    }
+
+    let _lock = lock_home()?;
+    let mut trust_store = load_trust_store()?;
+    for path in &paths {
+        if trust_store.deny(path) {
+            println!("No longer trusting {}", path.display());
+        } else {
+            println!("{} was not trusted", path.display());
+        }
+    }
+    trust_store
+        .save(&trust_file())
+        .context("failed to save trusted files")?;
+
+    Ok(())
+}
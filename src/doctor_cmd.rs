@@ -1,6 +1,11 @@
-use crate::environment::load_command_version_registry;
+use crate::command_version::{CommandVersion, CommandVersionRegistry, Layer};
+use crate::environment::{self, load_command_version_registry, load_config, lock_home};
+use crate::probe;
+use crate::use_file;
+use crate::version_order;
 use anyhow::Context;
 use dialoguer::Confirm;
+use std::env;
 use std::os::unix::fs::MetadataExt;
 use std::process;
 
@@ -14,6 +19,11 @@ pub fn run(fix_mode: FixMode) -> anyhow::Result<()> {
     let mut problem_count: u32 = 0;
     let mut fixed_count: u32 = 0;
 
+    // Probing can take a while. It's done before locking so that other alt
+    // commands aren't blocked in the meantime.
+    let probes = probe_all()?;
+
+    let _lock = lock_home()?;
     let mut command_version_registry = load_command_version_registry()?;
 
     for command_version in command_version_registry.iter().collect::<Vec<_>>() {
@@ -50,6 +60,10 @@ pub fn run(fix_mode: FixMode) -> anyhow::Result<()> {
         if has_problem {
             problem_count += 1;
 
+            if report_not_fixable(&command_version_registry, &command_version) {
+                continue;
+            }
+
             print_fix_available(&format!(
                 "Remove entry for {} version {}.",
                 command_version.command_name, command_version.version_name,
@@ -77,6 +91,76 @@ pub fn run(fix_mode: FixMode) -> anyhow::Result<()> {
         }
     }
 
+    // Binaries can be upgraded in place or be defined under the wrong version.
+    // Only a version that no longer matches its name is a problem, the rest
+    // is kept up to date in the probed version.
+    for (command_version, probed) in probes {
+        let Some(entry) = command_version_registry
+            .get_entry(&command_version.command_name, &command_version.version_name)
+        else {
+            continue;
+        };
+        if entry.path != command_version.path {
+            continue;
+        }
+
+        if version_order::is_prefix(&command_version.version_name, &probed) == Some(false) {
+            problem_count += 1;
+            print_problem(&format!(
+                "Bin for {} version {} ({}) reports version {}. You may want to \
+                re-define it under a different version.",
+                command_version.command_name,
+                command_version.version_name,
+                command_version.path.display(),
+                probed
+            ));
+            println!();
+            continue;
+        }
+
+        let Some(previous) = entry
+            .probed_version
+            .clone()
+            .filter(|previous| *previous != probed)
+        else {
+            continue;
+        };
+        print_info(&format!(
+            "Bin for {} version {} ({}) now reports version {} instead of {}.",
+            command_version.command_name,
+            command_version.version_name,
+            command_version.path.display(),
+            probed,
+            previous
+        ));
+
+        if report_not_fixable(&command_version_registry, &command_version) {
+            continue;
+        }
+
+        print_fix_available(&format!(
+            "Update the probed version of {} version {} to {}.",
+            command_version.command_name, command_version.version_name, probed
+        ));
+
+        // Not a problem so it doesn't count towards the fixes
+        if should_fix(&fix_mode) {
+            // The path is the same so the rest of the entry is kept
+            command_version_registry
+                .add(command_version.clone())?
+                .probed_version = Some(probed.clone());
+            command_version_registry
+                .save()
+                .context("Failed to save command version definitions")?;
+
+            print_fixed(&format!(
+                "Updated the probed version of {} version {} to {}.",
+                command_version.command_name, command_version.version_name, probed
+            ));
+        }
+        println!();
+    }
+
     if command_version_registry.is_empty() {
         problem_count += 1;
         print_problem(
@@ -88,6 +172,21 @@ pub fn run(fix_mode: FixMode) -> anyhow::Result<()> {
         println!();
     }
 
+    // Every use file counts, not just the closest one, since shims can be
+    // configured to fall back on the next file up the tree.
+    for path in use_file::find_all(&env::current_dir()?) {
+        if let Err(err) = use_file::load(&path) {
+            problem_count += 1;
+            print_problem(&format!(
+                "Use file {} can't be loaded: {}. Shims under {} won't use it.",
+                path.display(),
+                err,
+                path.parent().unwrap().display()
+            ));
+            println!();
+        }
+    }
+
     // TODO: check all used versions point to real versions
     // TODO: check that shims are defined
     // TODO: check that old shims are not left over
@@ -110,6 +209,45 @@ pub fn run(fix_mode: FixMode) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Only the user's definitions are ours to change. Versions from other files
+/// have to be fixed by whoever manages those files. Returns `true` when
+/// `command_version` is one of them.
+fn report_not_fixable(registry: &CommandVersionRegistry, command_version: &CommandVersion) -> bool {
+    let layer = registry.layer(&command_version.command_name, &command_version.version_name);
+    match layer {
+        Some(layer) if *layer != Layer::User => {
+            println!(
+                "{} version {} is defined in {}, can't be fixed here.",
+                command_version.command_name,
+                command_version.version_name,
+                environment::layer_file(layer).display()
+            );
+            println!();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Probe every version whose bin can be run
+fn probe_all() -> anyhow::Result<Vec<(CommandVersion, String)>> {
+    let config = load_config()?;
+    let registry = load_command_version_registry()?;
+
+    Ok(registry
+        .iter()
+        .filter(|command_version| command_version.path.is_file())
+        .filter_map(|command_version| {
+            let probed = probe::probe(
+                &command_version.command_name,
+                &command_version.path,
+                &config.probe,
+            )?;
+            Some((command_version, probed))
+        })
+        .collect())
+}
+
 fn should_fix(fix_mode: &FixMode) -> bool {
     match fix_mode {
         FixMode::Auto => {
@@ -135,6 +273,10 @@ fn print_problem(message: &str) {
     println!("{}: {}", console::style("Problem").bold().yellow(), message);
 }
 
+fn print_info(message: &str) {
+    println!("{}: {}", console::style("Info").bold().blue(), message);
+}
+
 fn print_fix_available(message: &str) {
     println!(
         "{}: {}",
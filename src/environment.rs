@@ -1,4 +1,7 @@
-use crate::command_version::CommandVersionRegistry;
+use crate::command_version::{CommandVersionRegistry, Layer};
+use crate::config::Config;
+use crate::safe_file::{self, DirLock};
+use crate::trust::TrustStore;
 use anyhow::Context;
 use std::env;
 use std::path::{Path, PathBuf};
@@ -6,6 +9,9 @@ use std::path::{Path, PathBuf};
 const DEFAULT_HOME: &str = ".config/alt";
 const DEFAULT_SHIM_DIR: &str = ".local/alt/shims";
 const DEFINITIONS_FILE_NAME: &str = "defs.toml";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const TRUST_FILE_NAME: &str = "trusted.toml";
+const DEFAULT_SYSTEM_DEFINITIONS_FILE: &str = "/etc/alt/defs.toml";
 
 pub fn home_dir() -> PathBuf {
     match env::var("ALT_HOME") {
@@ -30,8 +36,50 @@ pub fn definitions_file() -> PathBuf {
     home_dir().join(DEFINITIONS_FILE_NAME)
 }
 
+pub fn system_definitions_file() -> PathBuf {
+    env::var("ALT_SYSTEM_DEFS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_SYSTEM_DEFINITIONS_FILE))
+}
+
+/// The file that the versions of a layer are defined in
+pub fn layer_file(layer: &Layer) -> PathBuf {
+    match layer {
+        Layer::System => system_definitions_file(),
+        Layer::User => definitions_file(),
+        Layer::Include(path) | Layer::Project(path) => path.clone(),
+    }
+}
+
+pub fn config_file() -> PathBuf {
+    home_dir().join(CONFIG_FILE_NAME)
+}
+
+pub fn trust_file() -> PathBuf {
+    home_dir().join(TRUST_FILE_NAME)
+}
+
+pub fn load_trust_store() -> anyhow::Result<TrustStore> {
+    let path = trust_file();
+    TrustStore::load_or_default(&path)
+        .with_context(|| format!("failed to load trusted files from {}", path.display()))
+}
+
+pub fn load_config() -> anyhow::Result<Config> {
+    let path = config_file();
+    Config::load_or_default(&path)
+        .with_context(|| format!("failed to load config from {}", path.display()))
+}
+
+/// Lock the alt home directory while the definitions are being changed. This
+/// keeps concurrent alt commands from overwriting each other's changes.
+pub fn lock_home() -> anyhow::Result<DirLock> {
+    let dir = home_dir();
+    safe_file::lock_dir(&dir).with_context(|| format!("failed to lock {}", dir.display()))
+}
+
 pub fn load_command_version_registry() -> anyhow::Result<CommandVersionRegistry> {
-    CommandVersionRegistry::load_or_new(&definitions_file())
+    CommandVersionRegistry::load_layered(&system_definitions_file(), &definitions_file())
         .context("failed to load command version registry")
 }
 
@@ -1,19 +1,47 @@
-use crate::command::find_selected_binary;
-use crate::command::find_selected_version;
-use crate::environment::load_command_version_registry;
+use crate::command::{
+    find_binary, find_selected_version, find_system_bin, find_use_files, load_shim_registry,
+};
 use std::env;
 use std::os::unix::process::CommandExt;
 use std::process;
 use std::process::Command;
 
 pub fn run(command: &str, command_args: &[String]) -> anyhow::Result<()> {
-    let command_version_registry = load_command_version_registry()?;
+    // The use files are only looked up once so that problems with them are only
+    // reported once.
+    let use_files = find_use_files(command);
+    let command_version_registry = load_shim_registry(&use_files, command);
+    let version = match &command_version_registry {
+        Some(registry) => match find_selected_version(registry, &use_files, command) {
+            Ok(version) => version,
+            Err(err) => {
+                eprintln!("alt: {err}");
+                process::exit(1)
+            }
+        },
+        None => None,
+    };
 
-    match find_selected_binary(&command_version_registry, command) {
+    let bin = match &command_version_registry {
+        Some(registry) => find_binary(registry, command, version.as_deref()),
+        None => find_system_bin(command),
+    };
+
+    match bin {
         Some(bin) => {
-            let err = Command::new(&bin).args(command_args).exec();
+            let version_env = version
+                .as_ref()
+                .zip(command_version_registry.as_ref())
+                .and_then(|(version, registry)| registry.get_entry(command, version))
+                .map(|entry| entry.env.clone())
+                .unwrap_or_default();
+
+            let err = Command::new(&bin)
+                .args(command_args)
+                .envs(version_env)
+                .exec();
 
-            let pretty_command_version = match find_selected_version(command) {
+            let pretty_command_version = match version {
                 Some(version) => version,
                 None => "(not set, falling back on system version)".to_string(),
             };
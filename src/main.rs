@@ -1,20 +1,32 @@
+mod allow_cmd;
+mod check_cmd;
 mod checks;
 mod cli;
 mod command;
 mod command_version;
+mod config;
 mod def_cmd;
+mod deny_cmd;
 mod doctor_cmd;
 mod environment;
 mod exec_cmd;
+mod parse_error;
+mod probe;
+mod safe_file;
 mod scan;
 mod scan_cmd;
 mod shim;
 mod shim_cmd;
 mod show_cmd;
+mod trust;
 mod use_cmd;
 mod use_file;
+mod version_order;
+mod version_req;
 mod which_cmd;
 
+use anyhow::Context;
+use regex::Regex;
 use std::env;
 
 fn main() -> anyhow::Result<()> {
@@ -44,11 +56,32 @@ fn main() -> anyhow::Result<()> {
             }
             Some(("shim", _)) => shim_cmd::run()?,
             Some(("scan", matches)) => {
-                scan_cmd::run(matches.get_one::<String>("command").unwrap())?
+                let filter = matches
+                    .get_one::<String>("filter")
+                    .map(|filter| Regex::new(filter))
+                    .transpose()
+                    .context("invalid --filter regex")?;
+                let format = match matches.get_one::<String>("format").map(String::as_ref) {
+                    Some("human") => scan_cmd::OutputFormat::Human,
+                    Some("json") => scan_cmd::OutputFormat::Json,
+                    _ => unreachable!(),
+                };
+                scan_cmd::run(
+                    matches.get_one::<String>("command").map(String::as_ref),
+                    &scan_cmd::ScanOptions {
+                        yes: matches.get_flag("yes"),
+                        filter,
+                        dry_run: matches.get_flag("dry_run"),
+                        probe: matches.get_flag("probe"),
+                        update: matches.get_flag("update"),
+                        format,
+                    },
+                )?
             }
             Some(("use", matches)) => use_cmd::run(
                 matches.get_one::<String>("command").unwrap(),
                 matches.get_one::<String>("version").map(String::as_ref),
+                matches.get_flag("shared"),
             )?,
             Some(("show", _)) => show_cmd::run()?,
             Some(("doctor", matches)) => {
@@ -60,6 +93,16 @@ fn main() -> anyhow::Result<()> {
                 };
                 doctor_cmd::run(fix_mode)?
             }
+            Some(("allow", _)) => allow_cmd::run()?,
+            Some(("check", matches)) => {
+                let format = match matches.get_one::<String>("format").map(String::as_ref) {
+                    Some("human") => scan_cmd::OutputFormat::Human,
+                    Some("json") => scan_cmd::OutputFormat::Json,
+                    _ => unreachable!(),
+                };
+                check_cmd::run(&format)?
+            }
+            Some(("deny", _)) => deny_cmd::run()?,
             Some(("def", matches)) => def_cmd::run(
                 matches.get_one::<String>("command").unwrap(),
                 matches.get_one::<String>("version").unwrap(),
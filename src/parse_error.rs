@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+
+/// A TOML file that couldn't be parsed, with where the problem is in it
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("{}:{line}:{column}: {message}", path.display())]
+pub struct ParseError {
+    pub path: PathBuf,
+    /// 1-based line of the problem
+    pub line: usize,
+    /// 1-based column of the problem, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(path: &Path, contents: &str, err: &toml::de::Error) -> Self {
+        let offset = err.span().map_or(0, |span| span.start).min(contents.len());
+        let before = &contents[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        ParseError {
+            path: path.to_owned(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: err.message().trim_end().to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(contents: &str) -> ParseError {
+        let err = toml::from_str::<HashMap<String, String>>(contents).unwrap_err();
+        ParseError::new(Path::new("/project/.alt.toml"), contents, &err)
+    }
+
+    #[test]
+    fn error_has_line_and_column() {
+        let err = parse("node = \"18\"\npython \"3.11\"\n");
+
+        assert_eq!(err.line, 2);
+        assert_eq!(err.column, 8);
+        assert!(err.to_string().starts_with("/project/.alt.toml:2:8: "));
+    }
+
+    #[test]
+    fn error_on_wrong_type() {
+        let err = parse("node = 18\n");
+
+        assert_eq!((err.line, err.column), (1, 8));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::config::ProbeConfig;
+use crate::version_order;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    // The version can be glued to a prefix (`go1.21.5`) but not to another
+    // number. That keeps us from picking up the middle of a longer version.
+    static ref VERSION_REGEX: Regex = Regex::new(
+        r"(?:^|[^\d.])(?P<version>\d+\.\d+(?:\.\d+)?(?:-[0-9A-Za-z]+(?:\.[0-9A-Za-z]+)*)?)"
+    )
+    .unwrap();
+}
+
+/// Find the first semver looking thing in the output of a version flag.
+fn extract_version(output: &str) -> Option<String> {
+    VERSION_REGEX
+        .captures(output)
+        .map(|captures| captures["version"].to_owned())
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = vec![];
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// Run `bin arg` and return everything it printed. `None` is returned when the
+/// command fails or takes longer than `timeout`.
+fn run_with_timeout(bin: &Path, arg: &str, timeout: Duration) -> Option<String> {
+    let mut child = Command::new(bin)
+        .arg(arg)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    // The pipes are drained while we wait. Otherwise, a command with a lot
+    // of output would block forever on a full pipe.
+    let stdout = read_all(child.stdout.take()?);
+    let stderr = read_all(child.stderr.take()?);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+
+    if !status.success() {
+        return None;
+    }
+
+    // Some commands (java for one) print their version on stderr.
+    let mut output = stdout.join().ok()?;
+    output.extend(stderr.join().ok()?);
+    Some(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Run `bin` with the version flags configured for `command` until one of
+/// them prints a version.
+pub fn probe(command: &str, bin: &Path, config: &ProbeConfig) -> Option<String> {
+    config
+        .flags_for(command)
+        .into_iter()
+        .flat_map(|flag| run_with_timeout(bin, flag, config.timeout()))
+        .find_map(|output| extract_version(&output))
+}
+
+/// Check if the probed version is a more precise version of the name
+/// (`3` -> `3.11.7`).
+pub fn is_more_precise(version_name: &str, probed: &str) -> bool {
+    version_name != probed && version_order::is_prefix(version_name, probed) == Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use test_case::test_case;
+
+    #[test_case("Python 3.11.7", Some("3.11.7") ; "python")]
+    #[test_case("v18.19.0\n", Some("18.19.0") ; "node")]
+    #[test_case("go version go1.21.5 linux/amd64", Some("1.21.5") ; "go")]
+    #[test_case("rustc 1.75.0 (82e1608df 2023-12-21)", Some("1.75.0") ; "rustc")]
+    #[test_case("openjdk version \"17.0.9\" 2023-10-17", Some("17.0.9") ; "java")]
+    #[test_case("ruby 3.3.0 (2023-12-25 revision 5124f9ac75) [x86_64-linux]", Some("3.3.0") ; "ruby")]
+    #[test_case("Python 3.13.0-rc.1", Some("3.13.0-rc.1") ; "pre release")]
+    #[test_case("psql (PostgreSQL) 16", None ; "major only")]
+    #[test_case("this is thingy v1", None ; "no version")]
+    fn test_extract_version(output: &str, expected: Option<&str>) {
+        assert_eq!(extract_version(output).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_is_more_precise() {
+        assert!(is_more_precise("3", "3.11.7"));
+        assert!(!is_more_precise("3.11.7", "3.11.7"));
+        assert!(!is_more_precise("2", "3.11.7"));
+    }
+
+    fn write_script(dir: &Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn probe_tries_every_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = write_script(
+            dir.path(),
+            "thingy",
+            r#"[ "$1" = "-V" ] && echo "thingy 1.2.3" && exit 0; exit 1"#,
+        );
+
+        assert_eq!(
+            probe("thingy", &bin, &ProbeConfig::default()).as_deref(),
+            Some("1.2.3")
+        );
+    }
+
+    #[test]
+    fn probe_gives_up_after_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = write_script(dir.path(), "thingy", "sleep 5; echo 1.2.3");
+        let config = ProbeConfig {
+            timeout_ms: 50,
+            ..ProbeConfig::default()
+        };
+
+        let start = Instant::now();
+        assert_eq!(probe("thingy", &bin, &config), None);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
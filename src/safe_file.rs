@@ -0,0 +1,114 @@
+use fs2::FileExt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+/// Write `contents` to `path` without ever leaving a partially written file
+/// behind. The contents are written to a temporary file next to `path` which
+/// then replaces `path`. When `path` is a symlink, the file it points to is
+/// replaced instead of the symlink.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    // Dotfile managers commonly symlink files like `defs.toml`
+    let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut tmp_name = file_name.to_owned();
+    tmp_name.push(format!(".{}.tmp", process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let res = File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_ref())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path));
+
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    res
+}
+
+/// Exclusive advisory lock on a directory. The lock is released when dropped.
+#[derive(Debug)]
+pub struct DirLock {
+    _dir: File,
+}
+
+/// Lock a directory to serialize read-modify-write cycles on the files it
+/// contains. Since files are replaced by [`write_atomic`], locking the files
+/// themselves would not work: the lock would stay on the replaced file.
+pub fn lock_dir(dir: &Path) -> io::Result<DirLock> {
+    fs::create_dir_all(dir)?;
+    let dir = File::open(dir)?;
+    dir.lock_exclusive()?;
+    Ok(DirLock { _dir: dir })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    #[test]
+    fn write_atomic_replaces_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let path = workdir.path().join("file.toml");
+        fs::write(&path, "old contents")?;
+
+        write_atomic(&path, "new contents")?;
+
+        assert_eq!(fs::read_to_string(&path)?, "new contents");
+        assert_eq!(fs::read_dir(workdir.path())?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_atomic_keeps_symlink() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let target = workdir.path().join("dotfiles.toml");
+        let path = workdir.path().join("file.toml");
+        fs::write(&target, "old contents")?;
+        std::os::unix::fs::symlink(&target, &path)?;
+
+        write_atomic(&path, "new contents")?;
+
+        assert!(fs::symlink_metadata(&path)?.file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target)?, "new contents");
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_atomic_leaves_nothing_behind_on_error() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        // Can't replace a directory with a file
+        let path = workdir.path().join("dir");
+        fs::create_dir(&path)?;
+
+        assert!(write_atomic(&path, "contents").is_err());
+        assert_eq!(fs::read_dir(workdir.path())?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lock_dir_is_exclusive() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+
+        let lock = lock_dir(workdir.path())?;
+        let other = File::open(workdir.path())?;
+        assert!(other.try_lock_exclusive().is_err());
+
+        drop(lock);
+        assert!(other.try_lock_exclusive().is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use super::{ancestor_name, glob_under, is_executable, is_symlink, tool_dir, ScanWarning};
+use crate::command_version::CommandVersion;
+use std::path::*;
+
+/// asdf & mise share the same layout: `installs/<plugin>/<version>`. Every
+/// binary of every install is reported under its own name: plugins often
+/// install more than the command they're named after (`nodejs` installs
+/// `node`, `npm`, globally installed tools, ...).
+const INSTALLS_GLOB: &str = "installs/*/*";
+
+/// Plugins that keep their binaries somewhere else than `bin`. The asdf golang
+/// plugin keeps the go SDK in `go`.
+const PLUGIN_BIN_GLOBS: [(&str, &[&str]); 2] = [
+    ("golang", &["bin/*", "go/bin/*"]),
+    ("go", &["bin/*", "go/bin/*"]),
+];
+
+fn bin_globs(plugin: &str) -> &'static [&'static str] {
+    match PLUGIN_BIN_GLOBS.iter().find(|(name, _)| *name == plugin) {
+        Some((_, globs)) => globs,
+        None => &["bin/*"],
+    }
+}
+
+/// Parse the path of a binary of an install (`installs/<plugin>/<version>`)
+fn parse_version_path(install: &Path, path: PathBuf) -> Option<CommandVersion> {
+    let version = ancestor_name(install, 0)?;
+    let command = ancestor_name(&path, 0)?;
+
+    Some(CommandVersion::new(command, version, &path))
+}
+
+/// Scan the installs of an asdf or mise data directory
+fn scan_root(root: &Path, warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let installs: Vec<_> = glob_under(root, INSTALLS_GLOB, warnings)
+        .into_iter()
+        // mise keeps aliases to installs (`latest`, `20`, ...) as symlinks
+        .filter(|install| install.is_dir() && !is_symlink(install))
+        .collect();
+
+    let mut versions = vec![];
+    for install in installs {
+        let Some(plugin) = ancestor_name(&install, 1) else {
+            continue;
+        };
+        for pattern in bin_globs(plugin) {
+            for path in glob_under(&install, pattern, warnings) {
+                if is_executable(&path) {
+                    versions.extend(parse_version_path(&install, path));
+                }
+            }
+        }
+    }
+
+    versions
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let asdf = tool_dir("ASDF_DATA_DIR", ".asdf");
+    let mise = tool_dir("MISE_DATA_DIR", ".local/share/mise");
+
+    asdf.into_iter()
+        .chain(mise)
+        .flat_map(|root| scan_root(&root, warnings))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    #[test]
+    fn test_parse_asdf_path_node() {
+        let install = Path::new("/home/me/.asdf/installs/nodejs/20.11.0");
+        assert_eq!(
+            parse_version_path(install, install.join("bin/node")),
+            Some(CommandVersion::new(
+                "node",
+                "20.11.0",
+                Path::new("/home/me/.asdf/installs/nodejs/20.11.0/bin/node"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_asdf_path_erlang() {
+        let install = Path::new("/home/me/.asdf/installs/erlang/26.2.1");
+        assert_eq!(
+            parse_version_path(install, install.join("bin/erl")),
+            Some(CommandVersion::new(
+                "erl",
+                "26.2.1",
+                Path::new("/home/me/.asdf/installs/erlang/26.2.1/bin/erl"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_mise_path_python() {
+        let install = Path::new("/home/me/.local/share/mise/installs/python/3.12.1");
+        assert_eq!(
+            parse_version_path(install, install.join("bin/python3")),
+            Some(CommandVersion::new(
+                "python3",
+                "3.12.1",
+                Path::new("/home/me/.local/share/mise/installs/python/3.12.1/bin/python3"),
+            ))
+        );
+    }
+
+    fn create_bin(path: &Path) -> TestResult {
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, "")?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_reports_every_executable() -> TestResult {
+        let root = tempfile::tempdir()?;
+        let bin = root.path().join("installs/python/3.12.1/bin");
+        create_bin(&bin.join("python3.12"))?;
+        create_bin(&bin.join("pip3.12"))?;
+        fs::write(bin.join("README"), "")?;
+
+        let mut versions = scan_root(root.path(), &mut vec![]);
+        versions.sort();
+
+        assert_eq!(
+            versions,
+            vec![
+                CommandVersion::new("pip3.12", "3.12.1", &bin.join("pip3.12")),
+                CommandVersion::new("python3.12", "3.12.1", &bin.join("python3.12")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_asdf_golang_layout() -> TestResult {
+        let root = tempfile::tempdir()?;
+        let bin = root.path().join("installs/golang/1.22.0/go/bin");
+        create_bin(&bin.join("go"))?;
+        create_bin(&bin.join("gofmt"))?;
+
+        let mut versions = scan_root(root.path(), &mut vec![]);
+        versions.sort();
+
+        assert_eq!(
+            versions,
+            vec![
+                CommandVersion::new("go", "1.22.0", &bin.join("go")),
+                CommandVersion::new("gofmt", "1.22.0", &bin.join("gofmt")),
+            ]
+        );
+
+        Ok(())
+    }
+}
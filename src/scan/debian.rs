@@ -0,0 +1,352 @@
+use super::{ancestor_name, glob_under, is_executable, ScanWarning};
+use crate::command_version::CommandVersion;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::path::*;
+
+const ALTERNATIVES_DIR: &str = "/var/lib/dpkg/alternatives";
+const LLVM_DIR: &str = "/usr/lib";
+const POSTGRESQL_DIR: &str = "/usr/lib/postgresql";
+const GCC_LIB_DIR: &str = "/usr/lib/gcc";
+const GCC_DRIVERS: [&str; 6] = ["gcc", "g++", "cpp", "gfortran", "gccgo", "gcov"];
+
+lazy_static! {
+    static ref SUFFIX_VERSION_REGEX: Regex =
+        Regex::new(r"^[^\d.-]+-?(?P<version>\d+(?:\.\d+)*)$").unwrap();
+    static ref DASHED_VERSION_REGEX: Regex =
+        Regex::new(r"(?:^|-)(?P<version>\d+(?:\.\d+)*)(?:-|$)").unwrap();
+}
+
+/// An alternatives group as recorded by `update-alternatives`
+#[derive(Debug, PartialEq)]
+struct AlternativeGroup {
+    link: PathBuf,
+    alternatives: Vec<PathBuf>,
+}
+
+/// Parse a file from the dpkg alternatives database. These files look like:
+///
+/// ```text
+/// auto                 <- mode
+/// /usr/bin/java        <- link
+/// java.1.gz            <- slave name (repeated for every slave)
+/// /usr/share/man/...   <- slave link
+///                      <- end of slaves
+/// /usr/lib/jvm/...     <- alternative (repeated for every alternative)
+/// 1711                 <- priority
+/// /usr/lib/jvm/...     <- one path per slave (can be empty)
+///                      <- end of alternatives
+/// ```
+fn parse_alternatives(contents: &str) -> Option<AlternativeGroup> {
+    let mut lines = contents.lines();
+
+    let _mode = lines.next()?;
+    let link = PathBuf::from(lines.next()?);
+
+    let mut slave_count = 0;
+    loop {
+        let slave_name = lines.next()?;
+        if slave_name.is_empty() {
+            break;
+        }
+        let _slave_link = lines.next()?;
+        slave_count += 1;
+    }
+
+    let mut alternatives = vec![];
+    loop {
+        match lines.next() {
+            None | Some("") => break,
+            Some(alternative) => alternatives.push(PathBuf::from(alternative)),
+        }
+        let _priority = lines.next()?;
+        for _ in 0..slave_count {
+            let _slave_path = lines.next()?;
+        }
+    }
+
+    Some(AlternativeGroup { link, alternatives })
+}
+
+/// Check if `path` is in a `bin` or `sbin` directory
+fn is_in_bin_dir(path: &Path) -> bool {
+    matches!(ancestor_name(path, 1), Some("bin" | "sbin"))
+}
+
+/// Find a version in the path of an alternative: in its file name
+/// (`/usr/bin/python3.11`) or, for binaries in a `bin` directory, in the
+/// directories above it (`/usr/lib/jvm/java-17-openjdk/bin/java`). Other
+/// directories hold libraries and helpers whose directory versions don't say
+/// anything about them.
+fn alternative_version_name(path: &Path) -> Option<String> {
+    let dirs: Vec<_> = if is_in_bin_dir(path) {
+        path.ancestors().skip(2).collect()
+    } else {
+        vec![]
+    };
+
+    std::iter::once(path)
+        .chain(dirs)
+        .flat_map(|part| part.file_name()?.to_str())
+        .find_map(|part| {
+            SUFFIX_VERSION_REGEX
+                .captures(part)
+                .or_else(|| DASHED_VERSION_REGEX.captures(part))
+                .map(|captures| captures["version"].to_owned())
+        })
+}
+
+/// Alternatives without a version are other implementations of the command
+/// (`editor` -> `nano`, `vim.basic`). They are named after their file name
+/// when they are in a `bin` directory.
+fn implementation_name(path: &Path) -> Option<String> {
+    if is_in_bin_dir(path) {
+        path.file_name()?.to_str().map(str::to_owned)
+    } else {
+        None
+    }
+}
+
+/// dpkg also switches man pages, themes, libraries, ... Only the groups whose
+/// link is in a `bin` directory are commands.
+fn group_command_versions(group: AlternativeGroup) -> Vec<CommandVersion> {
+    if !is_in_bin_dir(&group.link) {
+        return vec![];
+    }
+    let command = match group.link.file_name().and_then(|name| name.to_str()) {
+        Some(command) => command.to_owned(),
+        None => return vec![],
+    };
+
+    group
+        .alternatives
+        .into_iter()
+        .flat_map(|path| {
+            let version = alternative_version_name(&path).or_else(|| implementation_name(&path))?;
+            Some(CommandVersion::new(&command, &version, &path))
+        })
+        .collect()
+}
+
+fn scan_alternatives(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    glob_under(Path::new(ALTERNATIVES_DIR), "*", warnings)
+        .into_iter()
+        .flat_map(|path| {
+            fs::read_to_string(&path)
+                .map_err(|err| warnings.push(ScanWarning::new(&path, err)))
+                .ok()
+        })
+        .flat_map(|contents| parse_alternatives(&contents))
+        .flat_map(group_command_versions)
+        .filter(|version| is_executable(&version.path))
+        .collect()
+}
+
+/// Parse a binary in a versioned directory like `/usr/lib/llvm-14/bin/clang`
+/// or `/usr/lib/postgresql/15/bin/psql`.
+fn parse_versioned_dir_path(path: PathBuf) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    let dir = ancestor_name(&path, 2)?;
+    let version = dir.strip_prefix("llvm-").unwrap_or(dir);
+
+    Some(CommandVersion::new(command, version, &path))
+}
+
+/// `/usr/lib/gcc/<triple>/<version>` only holds the internals of GCC. The
+/// drivers matching those versions live in `/usr/bin` (`gcc-12`, `g++-12`, ...).
+fn scan_gcc(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let mut versions: Vec<_> = glob_under(Path::new(GCC_LIB_DIR), "*/*", warnings)
+        .into_iter()
+        .flat_map(|dir| dir.file_name()?.to_str().map(str::to_owned))
+        .flat_map(|version| {
+            GCC_DRIVERS.iter().flat_map(move |driver| {
+                let path = Path::new("/usr/bin").join(format!("{driver}-{version}"));
+                if path.is_file() {
+                    Some(CommandVersion::new(driver, &version, &path))
+                } else {
+                    None
+                }
+            })
+        })
+        .collect();
+
+    // Cross compilers share the same versions and drivers
+    versions.sort();
+    versions.dedup();
+
+    versions
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let llvm = glob_under(Path::new(LLVM_DIR), "llvm-*/bin/*", warnings);
+    let postgresql = glob_under(Path::new(POSTGRESQL_DIR), "*/bin/*", warnings);
+    let versioned_dirs = llvm
+        .into_iter()
+        .chain(postgresql)
+        .flat_map(parse_versioned_dir_path);
+
+    scan_alternatives(warnings)
+        .into_iter()
+        .chain(versioned_dirs)
+        .chain(scan_gcc(warnings))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alternatives_with_slaves() {
+        let contents = concat!(
+            "auto\n",
+            "/usr/bin/java\n",
+            "java.1.gz\n",
+            "/usr/share/man/man1/java.1.gz\n",
+            "\n",
+            "/usr/lib/jvm/java-11-openjdk-amd64/bin/java\n",
+            "1111\n",
+            "/usr/lib/jvm/java-11-openjdk-amd64/man/man1/java.1.gz\n",
+            "/usr/lib/jvm/java-17-openjdk-amd64/bin/java\n",
+            "1711\n",
+            "\n",
+            "\n",
+        );
+
+        assert_eq!(
+            parse_alternatives(contents),
+            Some(AlternativeGroup {
+                link: PathBuf::from("/usr/bin/java"),
+                alternatives: vec![
+                    PathBuf::from("/usr/lib/jvm/java-11-openjdk-amd64/bin/java"),
+                    PathBuf::from("/usr/lib/jvm/java-17-openjdk-amd64/bin/java"),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_alternatives_without_slaves() {
+        let contents = concat!(
+            "manual\n",
+            "/usr/bin/editor\n",
+            "\n",
+            "/usr/bin/nano\n",
+            "40\n",
+            "/usr/bin/vim.basic\n",
+            "30\n",
+            "\n",
+        );
+
+        assert_eq!(
+            parse_alternatives(contents),
+            Some(AlternativeGroup {
+                link: PathBuf::from("/usr/bin/editor"),
+                alternatives: vec![
+                    PathBuf::from("/usr/bin/nano"),
+                    PathBuf::from("/usr/bin/vim.basic"),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_alternatives_truncated() {
+        assert_eq!(parse_alternatives("auto\n"), None);
+    }
+
+    #[test]
+    fn test_alternative_version_name_from_suffix() {
+        assert_eq!(
+            alternative_version_name(Path::new("/usr/bin/python3.11")),
+            Some("3.11".to_string())
+        );
+    }
+
+    #[test]
+    fn test_alternative_version_name_from_dir() {
+        assert_eq!(
+            alternative_version_name(Path::new("/usr/lib/jvm/java-17-openjdk-amd64/bin/java")),
+            Some("17".to_string())
+        );
+    }
+
+    #[test]
+    fn test_alternative_version_name_ignores_triple() {
+        assert_eq!(
+            alternative_version_name(Path::new("/usr/bin/x86_64-linux-gnu-gcc-12")),
+            Some("12".to_string())
+        );
+    }
+
+    #[test]
+    fn test_alternative_version_name_without_version() {
+        assert_eq!(
+            alternative_version_name(Path::new("/usr/bin/vim.basic")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_alternative_version_name_outside_bin_dir() {
+        assert_eq!(
+            alternative_version_name(Path::new("/usr/lib/jvm/java-17-openjdk-amd64/lib/jexec")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_group_without_versions_uses_file_names() {
+        let group = AlternativeGroup {
+            link: PathBuf::from("/usr/bin/editor"),
+            alternatives: vec![
+                PathBuf::from("/usr/bin/nano"),
+                PathBuf::from("/usr/bin/vim.basic"),
+                PathBuf::from("/usr/lib/jvm/java-17-openjdk-amd64/lib/jexec"),
+            ],
+        };
+
+        assert_eq!(
+            group_command_versions(group),
+            vec![
+                CommandVersion::new("editor", "nano", Path::new("/usr/bin/nano")),
+                CommandVersion::new("editor", "vim.basic", Path::new("/usr/bin/vim.basic")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_man_page_group_is_not_a_command() {
+        let group = AlternativeGroup {
+            link: PathBuf::from("/usr/share/man/man7/builtins.7.gz"),
+            alternatives: vec![PathBuf::from("/usr/share/man/man7/bash-builtins.7.gz")],
+        };
+
+        assert_eq!(group_command_versions(group), vec![]);
+    }
+
+    #[test]
+    fn test_parse_llvm_path_clang() {
+        assert_eq!(
+            parse_versioned_dir_path(PathBuf::from("/usr/lib/llvm-14/bin/clang")),
+            Some(CommandVersion::new(
+                "clang",
+                "14",
+                Path::new("/usr/lib/llvm-14/bin/clang")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_postgresql_path_psql() {
+        assert_eq!(
+            parse_versioned_dir_path(PathBuf::from("/usr/lib/postgresql/15/bin/psql")),
+            Some(CommandVersion::new(
+                "psql",
+                "15",
+                Path::new("/usr/lib/postgresql/15/bin/psql")
+            ))
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use super::{glob_under, tool_dir, ScanWarning};
+use crate::command_version::CommandVersion;
+use std::env;
+use std::fs;
+use std::path::*;
+
+const DL_SDK_DIR: &str = "sdk";
+const SYSTEM_GO_DIR: &str = "/usr/local/go";
+
+/// Go SDKs carry a `VERSION` file whose first line looks like `go1.21.5`
+fn parse_version_file(contents: &str) -> Option<String> {
+    let line = contents.lines().next()?.trim();
+    let version = line.strip_prefix("go").unwrap_or(line);
+
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_owned())
+    }
+}
+
+/// The version of an SDK is read from its `VERSION` file. When it's missing,
+/// the name of the SDK directory (`go1.21.5` or `1.21.5`) is used instead.
+fn sdk_version_name(sdk: &Path) -> Option<String> {
+    fs::read_to_string(sdk.join("VERSION"))
+        .ok()
+        .and_then(|contents| parse_version_file(&contents))
+        .or_else(|| {
+            let name = sdk.file_name()?.to_str()?;
+            Some(name.strip_prefix("go").unwrap_or(name).to_owned())
+        })
+}
+
+fn scan_sdk(sdk: &Path, warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let version = match sdk_version_name(sdk) {
+        Some(version) => version,
+        None => return vec![],
+    };
+
+    glob_under(sdk, "bin/*", warnings)
+        .into_iter()
+        .flat_map(|path| {
+            let command = path.file_name()?.to_str()?.to_owned();
+            Some(CommandVersion::new(&command, &version, &path))
+        })
+        .collect()
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    // SDKs installed through `go install golang.org/dl/go1.21.5`
+    let dl = env::var_os("HOME")
+        .map(|home| glob_under(&Path::new(&home).join(DL_SDK_DIR), "go*", warnings))
+        .unwrap_or_default();
+
+    let goenv = tool_dir("GOENV_ROOT", ".goenv")
+        .map(|root| glob_under(&root, "versions/*", warnings))
+        .unwrap_or_default();
+
+    dl.into_iter()
+        .chain(goenv)
+        .chain([PathBuf::from(SYSTEM_GO_DIR)])
+        .filter(|sdk| sdk.is_dir())
+        .flat_map(|sdk| scan_sdk(&sdk, warnings))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    #[test]
+    fn test_parse_version_file() {
+        let contents = "go1.21.5\ntime 2023-11-29T21:21:52Z\n";
+        assert_eq!(parse_version_file(contents), Some("1.21.5".to_string()));
+    }
+
+    #[test]
+    fn test_parse_version_file_empty() {
+        assert_eq!(parse_version_file(""), None);
+    }
+
+    #[test]
+    fn test_scan_sdk_with_version_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let sdk = workdir.path().join("go");
+        fs::create_dir_all(sdk.join("bin"))?;
+        fs::write(sdk.join("bin/go"), "")?;
+        fs::write(sdk.join("VERSION"), "go1.22.0\n")?;
+
+        assert_eq!(
+            scan_sdk(&sdk, &mut vec![]),
+            vec![CommandVersion::new("go", "1.22.0", &sdk.join("bin/go"))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_sdk_dl_without_version_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let sdk = workdir.path().join("go1.21.5");
+        fs::create_dir_all(sdk.join("bin"))?;
+        fs::write(sdk.join("bin/gofmt"), "")?;
+
+        assert_eq!(
+            scan_sdk(&sdk, &mut vec![]),
+            vec![CommandVersion::new(
+                "gofmt",
+                "1.21.5",
+                &sdk.join("bin/gofmt")
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_sdk_goenv_without_version_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let sdk = workdir.path().join("1.20.12");
+        fs::create_dir_all(sdk.join("bin"))?;
+        fs::write(sdk.join("bin/go"), "")?;
+
+        assert_eq!(
+            scan_sdk(&sdk, &mut vec![]),
+            vec![CommandVersion::new("go", "1.20.12", &sdk.join("bin/go"))]
+        );
+
+        Ok(())
+    }
+}
@@ -1,64 +1,138 @@
-use glob::glob;
-use std::path::*;
+use super::{ancestor_name, glob_under, ScanWarning};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use crate::command_version::CommandVersion;
 
-const HOMEBREW_GLOB: &str = "/usr/local/opt/*@*/bin/*";
-const HOMEBREW_OPT_GLOB: &str = "/opt/homebrew/opt/*@*/bin/*";
-const LINUXBREW_GLOB: &str = "/home/linuxbrew/.linuxbrew/opt/*@*/bin/*";
-
-fn extract_command_and_version(path: &Path) -> (String, String) {
-    let parts: Vec<_> = path.components().collect();
-    let num_parts = parts.len();
-
-    let command = match parts[num_parts - 1] {
-        Component::Normal(part) => String::from(part.to_str().unwrap()),
-        _ => panic!(),
-    };
-    let version = match parts[num_parts - 3] {
-        Component::Normal(part) => {
-            let package = part.to_str().unwrap();
-            let version = package.split('@').next_back().unwrap();
-            String::from(version)
-        }
-        _ => panic!(),
-    };
-
-    (command, version)
-}
+const DEFAULT_PREFIXES: [&str; 3] = ["/usr/local", "/opt/homebrew", "/home/linuxbrew/.linuxbrew"];
+const KEG_ONLY_GLOB: &str = "opt/*@*/bin/*";
+const CELLAR_KEGS_GLOB: &str = "*/*";
 
-fn parse_version_path(path: PathBuf) -> CommandVersion {
-    let (command_name, version_name) = extract_command_and_version(&path);
+/// Read the command & version from a keg-only path
+/// (`opt/<formula>@<version>/bin/<command>`)
+fn parse_version_path(path: PathBuf) -> Option<CommandVersion> {
+    let command_name = ancestor_name(&path, 0)?.to_owned();
+    let (_, version_name) = ancestor_name(&path, 2)?.rsplit_once('@')?;
+    let version_name = version_name.to_owned();
 
-    CommandVersion {
+    Some(CommandVersion {
         command_name,
         version_name,
         path,
+    })
+}
+
+/// Find the homebrew installs to scan as `(prefix, cellar)` pairs. When
+/// `HOMEBREW_PREFIX` is set, only that install is scanned. Otherwise, we look
+/// in the default prefixes.
+fn installs() -> Vec<(PathBuf, PathBuf)> {
+    match env::var_os("HOMEBREW_PREFIX") {
+        Some(prefix) if !prefix.is_empty() => {
+            let prefix = PathBuf::from(prefix);
+            let cellar = match env::var_os("HOMEBREW_CELLAR") {
+                Some(cellar) if !cellar.is_empty() => PathBuf::from(cellar),
+                _ => prefix.join("Cellar"),
+            };
+            vec![(prefix, cellar)]
+        }
+        _ => DEFAULT_PREFIXES
+            .iter()
+            .map(|prefix| (PathBuf::from(prefix), Path::new(prefix).join("Cellar")))
+            .collect(),
     }
 }
 
-pub fn scan(command: &str) -> Vec<CommandVersion> {
-    let homebrew_glob = glob(HOMEBREW_GLOB).unwrap();
-    let homebrew_opt_glob = glob(HOMEBREW_OPT_GLOB).unwrap();
-    let linuxbrew_glob = glob(LINUXBREW_GLOB).unwrap();
-    let paths = homebrew_glob.chain(homebrew_opt_glob).chain(linuxbrew_glob);
+/// Keg directories are named after the formula version with an optional
+/// revision (`3.12.1_1`). The revision is dropped.
+fn keg_version_name(keg: &str) -> &str {
+    match keg.rsplit_once('_') {
+        Some((version, revision)) if revision.chars().all(|c| c.is_ascii_digit()) => version,
+        _ => keg,
+    }
+}
+
+/// List every version of every formula in the cellar
+/// (`Cellar/<formula>/<version>/bin/*`). When `opt/<formula>` points to the
+/// keg, the binaries are reported through `opt/` so that they keep working
+/// after a `brew upgrade`.
+fn scan_cellar(
+    prefix: &Path,
+    cellar: &Path,
+    warnings: &mut Vec<ScanWarning>,
+) -> Vec<CommandVersion> {
+    glob_under(cellar, CELLAR_KEGS_GLOB, warnings)
+        .into_iter()
+        .flat_map(|keg| {
+            let formula = ancestor_name(&keg, 1)?.to_owned();
+            let version = keg_version_name(ancestor_name(&keg, 0)?).to_owned();
+
+            let opt = prefix.join("opt").join(&formula);
+            let is_linked = match (fs::canonicalize(&opt), fs::canonicalize(&keg)) {
+                (Ok(opt), Ok(keg)) => opt == keg,
+                _ => false,
+            };
 
-    paths
+            let bin_dir = if is_linked && formula.contains('@') {
+                // Already reported through the keg-only glob
+                return None;
+            } else if is_linked {
+                opt.join("bin")
+            } else {
+                keg.join("bin")
+            };
+
+            let versions = glob_under(&bin_dir, "*", warnings)
+                .into_iter()
+                .flat_map(|path| {
+                    let command = path.file_name()?.to_str()?.to_owned();
+                    Some(CommandVersion::new(&command, &version, &path))
+                })
+                .collect::<Vec<_>>();
+
+            Some(versions)
+        })
         .flatten()
-        .map(parse_version_path)
-        .filter(|c| c.command_name == command)
         .collect()
 }
 
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let mut versions = vec![];
+
+    for (prefix, cellar) in installs() {
+        for path in glob_under(&prefix, KEG_ONLY_GLOB, warnings) {
+            if path.to_str().is_none() {
+                warnings.push(ScanWarning::new(&path, "path is not valid UTF-8"));
+                continue;
+            }
+            match parse_version_path(path.clone()) {
+                Some(version) => versions.push(version),
+                None => warnings.push(ScanWarning::new(&path, "not a keg-only formula binary")),
+            }
+        }
+        versions.extend(scan_cellar(&prefix, &cellar, warnings));
+    }
+
+    versions
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::error::Error;
+    use std::os::unix::fs as unix_fs;
+
+    type TestResult = Result<(), Box<dyn Error>>;
 
     #[test]
     fn test_parse_version_path_node() {
         assert_eq!(
             parse_version_path(PathBuf::from("/usr/local/opt/node@8/bin/node")),
-            CommandVersion::new("node", "8", Path::new("/usr/local/opt/node@8/bin/node"),)
+            Some(CommandVersion::new(
+                "node",
+                "8",
+                Path::new("/usr/local/opt/node@8/bin/node"),
+            ))
         );
     }
 
@@ -66,7 +140,11 @@ mod tests {
     fn test_parse_version_path_php() {
         assert_eq!(
             parse_version_path(PathBuf::from("/usr/local/opt/php@5.6/bin/php")),
-            CommandVersion::new("php", "5.6", Path::new("/usr/local/opt/php@5.6/bin/php"))
+            Some(CommandVersion::new(
+                "php",
+                "5.6",
+                Path::new("/usr/local/opt/php@5.6/bin/php")
+            ))
         );
     }
 
@@ -74,11 +152,11 @@ mod tests {
     fn test_parse_opt_homebrew_path_node() {
         assert_eq!(
             parse_version_path(PathBuf::from("/opt/homebrew/opt/node@16/bin/node")),
-            CommandVersion::new(
+            Some(CommandVersion::new(
                 "node",
                 "16",
                 Path::new("/opt/homebrew/opt/node@16/bin/node"),
-            )
+            ))
         );
     }
 
@@ -86,7 +164,11 @@ mod tests {
     fn test_parse_opt_homebrew_path_php() {
         assert_eq!(
             parse_version_path(PathBuf::from("/opt/homebrew/opt/php@8.2/bin/php")),
-            CommandVersion::new("php", "8.2", Path::new("/opt/homebrew/opt/php@8.2/bin/php")),
+            Some(CommandVersion::new(
+                "php",
+                "8.2",
+                Path::new("/opt/homebrew/opt/php@8.2/bin/php")
+            ))
         );
     }
 
@@ -96,11 +178,84 @@ mod tests {
             parse_version_path(PathBuf::from(
                 "/home/linuxbrew/.linuxbrew/opt/node@8/bin/node"
             )),
-            CommandVersion::new(
+            Some(CommandVersion::new(
                 "node",
                 "8",
                 Path::new("/home/linuxbrew/.linuxbrew/opt/node@8/bin/node"),
-            )
+            ))
         );
     }
+
+    #[test]
+    fn test_parse_version_path_outside_of_keg() {
+        assert_eq!(parse_version_path(PathBuf::from("/bin/node")), None);
+        assert_eq!(
+            parse_version_path(PathBuf::from("/usr/local/opt/node/bin/node")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_keg_version_name() {
+        assert_eq!(keg_version_name("21.5.0"), "21.5.0");
+        assert_eq!(keg_version_name("3.12.1_1"), "3.12.1");
+        assert_eq!(keg_version_name("1.2_beta"), "1.2_beta");
+    }
+
+    #[test]
+    fn test_scan_cellar_prefers_opt_for_linked_keg() -> TestResult {
+        let prefix = tempfile::tempdir()?;
+        let cellar = prefix.path().join("Cellar");
+        let keg = cellar.join("node/21.5.0");
+        fs::create_dir_all(keg.join("bin"))?;
+        fs::write(keg.join("bin/node"), "")?;
+        fs::create_dir_all(prefix.path().join("opt"))?;
+        unix_fs::symlink(&keg, prefix.path().join("opt/node"))?;
+
+        assert_eq!(
+            scan_cellar(prefix.path(), &cellar, &mut vec![]),
+            vec![CommandVersion::new(
+                "node",
+                "21.5.0",
+                &prefix.path().join("opt/node/bin/node")
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_cellar_uses_cellar_for_unlinked_keg() -> TestResult {
+        let prefix = tempfile::tempdir()?;
+        let cellar = prefix.path().join("Cellar");
+        let keg = cellar.join("python@3.11/3.11.6_1");
+        fs::create_dir_all(keg.join("bin"))?;
+        fs::write(keg.join("bin/python3"), "")?;
+
+        assert_eq!(
+            scan_cellar(prefix.path(), &cellar, &mut vec![]),
+            vec![CommandVersion::new(
+                "python3",
+                "3.11.6",
+                &keg.join("bin/python3")
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_cellar_skips_linked_keg_only_formula() -> TestResult {
+        let prefix = tempfile::tempdir()?;
+        let cellar = prefix.path().join("Cellar");
+        let keg = cellar.join("node@18/18.19.0");
+        fs::create_dir_all(keg.join("bin"))?;
+        fs::write(keg.join("bin/node"), "")?;
+        fs::create_dir_all(prefix.path().join("opt"))?;
+        unix_fs::symlink(&keg, prefix.path().join("opt/node@18"))?;
+
+        assert_eq!(scan_cellar(prefix.path(), &cellar, &mut vec![]), vec![]);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,126 @@
+use super::{glob_under, is_symlink, tool_dir, ScanWarning};
+use crate::command_version::CommandVersion;
+use std::env;
+use std::fs;
+use std::path::*;
+
+const SYSTEM_JVM_DIR: &str = "/usr/lib/jvm";
+const USER_JDKS_DIR: &str = ".jdks";
+
+/// Extract the version from the contents of a JDK's `release` file. The
+/// relevant line looks like `JAVA_VERSION="17.0.9"`.
+fn parse_release_file(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("JAVA_VERSION="))
+        .map(|version| version.trim_matches('"').to_owned())
+        .filter(|version| !version.is_empty())
+}
+
+fn install_version_name(install: &Path) -> Option<String> {
+    fs::read_to_string(install.join("release"))
+        .ok()
+        .and_then(|contents| parse_release_file(&contents))
+        .or_else(|| install.file_name()?.to_str().map(str::to_owned))
+}
+
+fn scan_install(install: &Path, warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let version = match install_version_name(install) {
+        Some(version) => version,
+        None => return vec![],
+    };
+
+    glob_under(install, "bin/*", warnings)
+        .into_iter()
+        .flat_map(|path| {
+            let command = path.file_name()?.to_str()?.to_owned();
+            Some(CommandVersion::new(&command, &version, &path))
+        })
+        .collect()
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let system = glob_under(Path::new(SYSTEM_JVM_DIR), "*", warnings);
+
+    // SDKMAN installs java as well as other JVM tools (gradle, maven,
+    // kotlin, ...) the same way: `candidates/<candidate>/<version>`.
+    let sdkman = tool_dir("SDKMAN_DIR", ".sdkman")
+        .map(|root| glob_under(&root, "candidates/*/*", warnings))
+        .unwrap_or_default();
+
+    let jdks = env::var_os("HOME")
+        .map(|home| glob_under(&Path::new(&home).join(USER_JDKS_DIR), "*", warnings))
+        .unwrap_or_default();
+
+    // Aliases like `/usr/lib/jvm/default-java` or SDKMAN's `current` are
+    // skipped to avoid reporting an install twice.
+    system
+        .into_iter()
+        .chain(sdkman)
+        .chain(jdks)
+        .filter(|install| install.is_dir() && !is_symlink(install))
+        .flat_map(|install| scan_install(&install, warnings))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    #[test]
+    fn test_parse_release_file() {
+        let contents = concat!(
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\n",
+            "JAVA_VERSION=\"17.0.9\"\n",
+            "JAVA_VERSION_DATE=\"2023-10-17\"\n",
+        );
+        assert_eq!(parse_release_file(contents), Some("17.0.9".to_string()));
+    }
+
+    #[test]
+    fn test_parse_release_file_without_version() {
+        assert_eq!(parse_release_file("IMPLEMENTOR=\"Someone\"\n"), None);
+    }
+
+    #[test]
+    fn test_scan_install_with_release_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let install = workdir.path().join("java-17-openjdk-amd64");
+        fs::create_dir_all(install.join("bin"))?;
+        fs::write(install.join("bin/java"), "")?;
+        fs::write(install.join("release"), "JAVA_VERSION=\"17.0.9\"\n")?;
+
+        assert_eq!(
+            scan_install(&install, &mut vec![]),
+            vec![CommandVersion::new(
+                "java",
+                "17.0.9",
+                &install.join("bin/java")
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_install_without_release_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let install = workdir.path().join("8.5");
+        fs::create_dir_all(install.join("bin"))?;
+        fs::write(install.join("bin/gradle"), "")?;
+
+        assert_eq!(
+            scan_install(&install, &mut vec![]),
+            vec![CommandVersion::new(
+                "gradle",
+                "8.5",
+                &install.join("bin/gradle")
+            )]
+        );
+
+        Ok(())
+    }
+}
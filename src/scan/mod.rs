@@ -1,2 +1,297 @@
+pub mod asdf;
+pub mod debian;
+pub mod go;
 pub mod homebrew;
+pub mod jvm;
+pub mod nix;
+pub mod node;
 pub mod path_suffix;
+pub mod python;
+pub mod ruby;
+pub mod rustup;
+
+use crate::command_version::CommandVersion;
+use glob::{glob_with, MatchOptions, Pattern};
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// A problem that came up while scanning. Problems don't stop the scan, they
+/// are reported to the user once it's done.
+#[derive(Debug, PartialEq)]
+pub struct ScanWarning {
+    pub path: Option<PathBuf>,
+    pub reason: String,
+}
+
+impl ScanWarning {
+    fn new(path: &Path, reason: impl Display) -> Self {
+        ScanWarning {
+            path: Some(path.to_owned()),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl Display for ScanWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path.display(), self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub versions: Vec<CommandVersion>,
+    pub warnings: Vec<ScanWarning>,
+    /// Name of the scanner that found each path
+    pub sources: HashMap<PathBuf, &'static str>,
+}
+
+type Scanner = fn(&mut Vec<ScanWarning>) -> Vec<CommandVersion>;
+
+const SCANNERS: [(&str, Scanner); 11] = [
+    ("path_suffix", path_suffix::scan),
+    ("homebrew", homebrew::scan),
+    ("node", node::scan),
+    ("python", python::scan),
+    ("ruby", ruby::scan),
+    ("rustup", rustup::scan),
+    ("jvm", jvm::scan),
+    ("asdf", asdf::scan),
+    ("nix", nix::scan),
+    ("debian", debian::scan),
+    ("go", go::scan),
+];
+
+/// Run every scanner. When `command` is given, only the versions of that
+/// command are kept.
+pub fn scan(command: Option<&str>) -> ScanReport {
+    let mut versions = vec![];
+    let mut warnings = vec![];
+    let mut sources = HashMap::new();
+
+    for (name, scanner) in SCANNERS {
+        for version in scanner(&mut warnings) {
+            if command.is_none_or(|command| version.command_name == command) {
+                sources.entry(version.path.clone()).or_insert(name);
+                versions.push(version);
+            }
+        }
+    }
+
+    ScanReport {
+        versions: merge_duplicates(versions),
+        warnings,
+        sources,
+    }
+}
+
+/// Resolve every symlink in `path`. This lets us tell that two different paths
+/// point to the same binary (`/usr/bin/python3` & `/usr/bin/python3.11`).
+pub fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// Merge the versions of a command that point to the same binary. Scanners
+/// often overlap and the same binary is often reachable through multiple
+/// names. The version with the most precise name (`3.11` over `3`) is kept.
+fn merge_duplicates(versions: Vec<CommandVersion>) -> Vec<CommandVersion> {
+    let mut merged: Vec<CommandVersion> = vec![];
+    let mut seen: HashMap<(String, PathBuf), usize> = HashMap::new();
+
+    for version in versions {
+        let key = (version.command_name.clone(), canonical_path(&version.path));
+
+        match seen.get(&key) {
+            Some(&i) => {
+                if precision(&version.version_name) > precision(&merged[i].version_name) {
+                    merged[i] = version;
+                }
+            }
+            None => {
+                seen.insert(key, merged.len());
+                merged.push(version);
+            }
+        }
+    }
+
+    merged
+}
+
+fn precision(version: &str) -> usize {
+    version.split('.').count()
+}
+
+/// Resolve the root directory of a version manager. The directory is read from
+/// the `var` environment variable when it's set and defaults to `default`
+/// relative to the user's home directory.
+fn tool_dir(var: &str, default: &str) -> Option<PathBuf> {
+    match env::var_os(var) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").map(|home| Path::new(&home).join(default)),
+    }
+}
+
+/// List the paths matching `pattern` under `root`. Special characters in
+/// `root` are escaped so that only `pattern` is treated as a glob. Hidden files
+/// (wrapped binaries like `.node-wrapped` for example) are not matched.
+/// Directories that can't be read are reported in `warnings`.
+fn glob_under(root: &Path, pattern: &str, warnings: &mut Vec<ScanWarning>) -> Vec<PathBuf> {
+    let root_str = match root.to_str() {
+        Some(root) => Pattern::escape(root),
+        None => {
+            warnings.push(ScanWarning::new(root, "path is not valid UTF-8"));
+            return vec![];
+        }
+    };
+
+    let options = MatchOptions {
+        require_literal_leading_dot: true,
+        ..MatchOptions::new()
+    };
+
+    let paths = match glob_with(&format!("{root_str}/{pattern}"), options) {
+        Ok(paths) => paths,
+        Err(err) => {
+            warnings.push(ScanWarning::new(root, err));
+            return vec![];
+        }
+    };
+
+    paths
+        .filter_map(|path| {
+            path.map_err(|err| warnings.push(ScanWarning::new(err.path(), err.error())))
+                .ok()
+        })
+        .collect()
+}
+
+/// Get the name of the `n`th ancestor of `path`. The file name itself is the
+/// 0th ancestor.
+fn ancestor_name(path: &Path, n: usize) -> Option<&str> {
+    path.ancestors().nth(n)?.file_name()?.to_str()
+}
+
+/// Check if `path` is a symlink. Version managers often keep aliases to their
+/// installs (`current`, `latest`, ...) as symlinks.
+fn is_symlink(path: &Path) -> bool {
+    path.symlink_metadata()
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Check if `path` is an executable file, following symlinks
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+    use std::os::unix::fs as unix_fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    #[test]
+    fn merge_duplicates_keeps_most_precise_version() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let python = workdir.path().join("python3.11");
+        fs::write(&python, "")?;
+        unix_fs::symlink(&python, workdir.path().join("python3"))?;
+
+        let res = merge_duplicates(vec![
+            CommandVersion::new("python", "3", &workdir.path().join("python3")),
+            CommandVersion::new("python", "3.11", &python),
+        ]);
+
+        assert_eq!(res, vec![CommandVersion::new("python", "3.11", &python)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_duplicates_keeps_first_of_equally_precise_versions() {
+        let res = merge_duplicates(vec![
+            CommandVersion::new("node", "18", Path::new("/does/not/exist/node")),
+            CommandVersion::new("node", "lts", Path::new("/does/not/exist/node")),
+        ]);
+
+        assert_eq!(
+            res,
+            vec![CommandVersion::new(
+                "node",
+                "18",
+                Path::new("/does/not/exist/node")
+            )]
+        );
+    }
+
+    #[test]
+    fn merge_duplicates_keeps_different_commands() {
+        let res = merge_duplicates(vec![
+            CommandVersion::new("gcc", "12", Path::new("/does/not/exist/gcc-12")),
+            CommandVersion::new("cc", "12", Path::new("/does/not/exist/gcc-12")),
+        ]);
+
+        assert_eq!(res.len(), 2);
+    }
+
+    #[test]
+    fn tool_dir_reads_env_var() {
+        env::set_var("ALT_TEST_TOOL_DIR_SET", "/path/to/tool");
+        assert_eq!(
+            tool_dir("ALT_TEST_TOOL_DIR_SET", ".tool"),
+            Some(PathBuf::from("/path/to/tool"))
+        );
+    }
+
+    #[test]
+    fn tool_dir_defaults_to_home() {
+        env::remove_var("ALT_TEST_TOOL_DIR_UNSET");
+        assert_eq!(
+            tool_dir("ALT_TEST_TOOL_DIR_UNSET", ".tool"),
+            Some(Path::new(&env::var("HOME").unwrap()).join(".tool"))
+        );
+    }
+
+    #[test]
+    fn glob_under_reports_unreadable_dirs() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let locked = workdir.path().join("locked");
+        fs::create_dir_all(locked.join("bin"))?;
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+
+        // Root can read anything which makes this test meaningless
+        if fs::read_dir(&locked).is_ok() {
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+            return Ok(());
+        }
+
+        let mut warnings = vec![];
+        let res = glob_under(workdir.path(), "*/*", &mut warnings);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+
+        assert_eq!(res, Vec::<PathBuf>::new());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, Some(locked));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ancestor_name_of_file() {
+        assert_eq!(ancestor_name(Path::new("/a/b/c"), 0), Some("c"));
+        assert_eq!(ancestor_name(Path::new("/a/b/c"), 2), Some("a"));
+        assert_eq!(ancestor_name(Path::new("/a/b/c"), 3), None);
+    }
+}
@@ -0,0 +1,152 @@
+use super::{ancestor_name, glob_under, ScanWarning};
+use crate::command_version::CommandVersion;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::*;
+
+const USER_PROFILE: &str = ".nix-profile";
+const USER_STATE_PROFILES: &str = ".local/state/nix/profiles";
+const PROFILES_DIR: &str = "/nix/var/nix/profiles";
+const GCROOTS_DIR: &str = "/nix/var/nix/gcroots";
+
+lazy_static! {
+    // Store entries are named `<hash>-<name>-<version>`. Like nix's
+    // `parseDrvName`, the version starts at the first dash that is not followed
+    // by a letter.
+    static ref STORE_ENTRY_REGEX: Regex =
+        Regex::new(r"^[0-9a-z]{32}-(?P<name>.+?)-(?P<version>[^a-zA-Z-].*)$").unwrap();
+}
+
+/// Parse a binary in the nix store (`/nix/store/<hash>-<name>-<version>/bin/<cmd>`)
+fn parse_store_path(path: PathBuf) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    if ancestor_name(&path, 1)? != "bin" {
+        return None;
+    }
+
+    let entry = ancestor_name(&path, 2)?;
+    let captures = STORE_ENTRY_REGEX.captures(entry)?;
+
+    Some(CommandVersion::new(command, &captures["version"], &path))
+}
+
+fn profiles(warnings: &mut Vec<ScanWarning>) -> Vec<PathBuf> {
+    let mut profiles = vec![];
+
+    if let Some(home) = env::var_os("HOME") {
+        let home = Path::new(&home);
+        profiles.push(home.join(USER_PROFILE));
+        profiles.extend(glob_under(&home.join(USER_STATE_PROFILES), "*", warnings));
+    }
+
+    // Old generations (`profile-42-link`) are included since they often hold
+    // older versions of the same packages.
+    profiles.push(Path::new(PROFILES_DIR).join("default"));
+    profiles.extend(glob_under(
+        Path::new(PROFILES_DIR),
+        "per-user/*/*",
+        warnings,
+    ));
+
+    profiles
+}
+
+/// Garbage collector roots point to things in the store that the user wants to
+/// keep around (`nix build` results, `nix-shell` environments, ...).
+fn gcroots(warnings: &mut Vec<ScanWarning>) -> Vec<PathBuf> {
+    glob_under(Path::new(GCROOTS_DIR), "auto/*", warnings)
+        .into_iter()
+        .flat_map(fs::canonicalize)
+        .collect()
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let mut seen = HashSet::new();
+
+    // Profiles & gcroots are full of symlinks to the actual packages in the
+    // store. Resolving them is what lets us find the package versions.
+    let mut roots = profiles(warnings);
+    roots.extend(gcroots(warnings));
+
+    roots
+        .into_iter()
+        .flat_map(|root| glob_under(&root, "bin/*", warnings))
+        .flat_map(fs::canonicalize)
+        .flat_map(parse_store_path)
+        // The same version is often found through multiple profiles or
+        // multiple store paths (different builds of the same package).
+        .filter(|c| seen.insert((c.command_name.clone(), c.version_name.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_store_path_node() {
+        assert_eq!(
+            parse_store_path(PathBuf::from(
+                "/nix/store/0c5ppzs8k4jbw1m0c8ya7d8rls2y3ysq-nodejs-18.17.1/bin/node"
+            )),
+            Some(CommandVersion::new(
+                "node",
+                "18.17.1",
+                Path::new("/nix/store/0c5ppzs8k4jbw1m0c8ya7d8rls2y3ysq-nodejs-18.17.1/bin/node"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_store_path_name_with_digits() {
+        assert_eq!(
+            parse_store_path(PathBuf::from(
+                "/nix/store/7v9x8mg5wdnqqfsj5mv8bkbdcy6zk5v9-python3-3.11.6/bin/python3"
+            )),
+            Some(CommandVersion::new(
+                "python3",
+                "3.11.6",
+                Path::new("/nix/store/7v9x8mg5wdnqqfsj5mv8bkbdcy6zk5v9-python3-3.11.6/bin/python3"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_store_path_name_with_dashes() {
+        assert_eq!(
+            parse_store_path(PathBuf::from(
+                "/nix/store/4jbw1m0c8ya7d8rls2y3ysq0c5ppzs8k-rustc-wrapper-1.73.0/bin/rustc"
+            )),
+            Some(CommandVersion::new(
+                "rustc",
+                "1.73.0",
+                Path::new(
+                    "/nix/store/4jbw1m0c8ya7d8rls2y3ysq0c5ppzs8k-rustc-wrapper-1.73.0/bin/rustc"
+                ),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_store_path_without_version() {
+        assert_eq!(
+            parse_store_path(PathBuf::from(
+                "/nix/store/4jbw1m0c8ya7d8rls2y3ysq0c5ppzs8k-user-environment/bin/node"
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_store_path_outside_bin() {
+        assert_eq!(
+            parse_store_path(PathBuf::from(
+                "/nix/store/0c5ppzs8k4jbw1m0c8ya7d8rls2y3ysq-nodejs-18.17.1/lib/node"
+            )),
+            None
+        );
+    }
+}
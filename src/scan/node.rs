@@ -0,0 +1,133 @@
+use super::{ancestor_name, glob_under, tool_dir, ScanWarning};
+use crate::command_version::CommandVersion;
+use std::path::*;
+
+/// A place where a node version manager keeps its installs. `pattern` is
+/// relative to the root directory of the version manager and `version_depth`
+/// is the position of the version directory counting back from the binary.
+struct Layout {
+    var: &'static str,
+    default_dir: &'static str,
+    pattern: &'static str,
+    version_depth: usize,
+}
+
+const NVM: Layout = Layout {
+    var: "NVM_DIR",
+    default_dir: ".nvm",
+    pattern: "versions/node/*/bin/*",
+    version_depth: 2,
+};
+const FNM: Layout = Layout {
+    var: "FNM_DIR",
+    default_dir: ".local/share/fnm",
+    pattern: "node-versions/*/installation/bin/*",
+    version_depth: 3,
+};
+const VOLTA: Layout = Layout {
+    var: "VOLTA_HOME",
+    default_dir: ".volta",
+    pattern: "tools/image/node/*/bin/*",
+    version_depth: 2,
+};
+
+fn clean_version(version: &str) -> &str {
+    match version.strip_prefix('v') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => version,
+    }
+}
+
+fn parse_version_path(path: PathBuf, layout: &Layout) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    let version = ancestor_name(&path, layout.version_depth)?;
+
+    Some(CommandVersion::new(command, clean_version(version), &path))
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    [NVM, FNM, VOLTA]
+        .iter()
+        .flat_map(|layout| {
+            tool_dir(layout.var, layout.default_dir)
+                .map(|root| glob_under(&root, layout.pattern, warnings))
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(move |path| parse_version_path(path, layout))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nvm_path_node() {
+        assert_eq!(
+            parse_version_path(
+                PathBuf::from("/home/me/.nvm/versions/node/v18.17.0/bin/node"),
+                &NVM
+            ),
+            Some(CommandVersion::new(
+                "node",
+                "18.17.0",
+                Path::new("/home/me/.nvm/versions/node/v18.17.0/bin/node"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_nvm_path_npm() {
+        assert_eq!(
+            parse_version_path(
+                PathBuf::from("/home/me/.nvm/versions/node/v20.10.0/bin/npm"),
+                &NVM
+            ),
+            Some(CommandVersion::new(
+                "npm",
+                "20.10.0",
+                Path::new("/home/me/.nvm/versions/node/v20.10.0/bin/npm"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_fnm_path_node() {
+        assert_eq!(
+            parse_version_path(
+                PathBuf::from(
+                    "/home/me/.local/share/fnm/node-versions/v16.20.2/installation/bin/node"
+                ),
+                &FNM
+            ),
+            Some(CommandVersion::new(
+                "node",
+                "16.20.2",
+                Path::new("/home/me/.local/share/fnm/node-versions/v16.20.2/installation/bin/node"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_volta_path_node() {
+        assert_eq!(
+            parse_version_path(
+                PathBuf::from("/home/me/.volta/tools/image/node/18.19.0/bin/node"),
+                &VOLTA
+            ),
+            Some(CommandVersion::new(
+                "node",
+                "18.19.0",
+                Path::new("/home/me/.volta/tools/image/node/18.19.0/bin/node"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_clean_version_keeps_non_numeric_names() {
+        assert_eq!(clean_version("v18.17.0"), "18.17.0");
+        assert_eq!(clean_version("18.17.0"), "18.17.0");
+        assert_eq!(clean_version("very-custom"), "very-custom");
+    }
+}
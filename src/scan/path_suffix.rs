@@ -1,3 +1,4 @@
+use super::ScanWarning;
 use crate::command_version::CommandVersion;
 use crate::environment;
 use lazy_static::lazy_static;
@@ -12,9 +13,9 @@ lazy_static! {
 }
 
 fn parse_command_version(bin: PathBuf) -> Option<CommandVersion> {
-    let name = String::from(bin.file_name().unwrap().to_str().unwrap());
+    let name = bin.file_name()?.to_str()?;
 
-    COMMAND_VERSION_REGEX.captures(&name).map(|captures| {
+    COMMAND_VERSION_REGEX.captures(name).map(|captures| {
         CommandVersion::new(
             captures.name("command").unwrap().as_str(),
             captures.name("version").unwrap().as_str(),
@@ -23,18 +24,53 @@ fn parse_command_version(bin: PathBuf) -> Option<CommandVersion> {
     })
 }
 
-pub fn scan(command: &str) -> Vec<CommandVersion> {
-    let path = env::var("PATH").expect("env var PATH is not defined");
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let path = match env::var_os("PATH") {
+        Some(path) => path,
+        None => {
+            warnings.push(ScanWarning {
+                path: None,
+                reason: String::from("env var PATH is not defined"),
+            });
+            return vec![];
+        }
+    };
     let shim_dir = environment::shim_dir();
 
-    env::split_paths(&path)
-        .filter(|p| p != &shim_dir)
-        .filter(|p| p.is_dir())
-        .flat_map(|p| fs::read_dir(p).unwrap())
-        .map(|bin| bin.unwrap().path())
-        .flat_map(parse_command_version)
-        .filter(|c| c.command_name == command)
-        .collect()
+    let mut versions = vec![];
+
+    for dir in env::split_paths(&path) {
+        if dir == shim_dir || !dir.is_dir() {
+            continue;
+        }
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warnings.push(ScanWarning::new(&dir, err));
+                continue;
+            }
+        };
+
+        for entry in entries {
+            let bin = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => {
+                    warnings.push(ScanWarning::new(&dir, err));
+                    continue;
+                }
+            };
+
+            if bin.file_name().and_then(|name| name.to_str()).is_none() {
+                warnings.push(ScanWarning::new(&bin, "file name is not valid UTF-8"));
+                continue;
+            }
+
+            versions.extend(parse_command_version(bin));
+        }
+    }
+
+    versions
 }
 
 #[cfg(test)]
@@ -0,0 +1,210 @@
+use super::{ancestor_name, glob_under, tool_dir, ScanWarning};
+use crate::command_version::CommandVersion;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::*;
+
+const CONDA_INSTALL_DIRS: [&str; 4] = ["miniconda3", "anaconda3", "miniforge3", "mambaforge"];
+const CONDA_ENVIRONMENTS_FILE: &str = ".conda/environments.txt";
+
+lazy_static! {
+    static ref UV_INSTALL_REGEX: Regex =
+        Regex::new(r"^(?P<implementation>[a-z]+)-(?P<version>\d+(?:\.\d+)*[^-]*)-").unwrap();
+}
+
+/// pyenv names version directories after the version itself (`3.11.7`,
+/// `miniconda3-latest`, `pypy3.10-7.3.15`, ...)
+fn parse_pyenv_path(path: PathBuf) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    let version = ancestor_name(&path, 2)?;
+
+    Some(CommandVersion::new(command, version, &path))
+}
+
+/// uv names install directories like `cpython-3.11.7-linux-x86_64-gnu`. We
+/// drop the platform and only keep the implementation when it's not CPython.
+fn parse_uv_path(path: PathBuf) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    let install = ancestor_name(&path, 2)?;
+
+    let version = match UV_INSTALL_REGEX.captures(install) {
+        Some(captures) => match (&captures["implementation"], &captures["version"]) {
+            ("cpython", version) => version.to_owned(),
+            (implementation, version) => format!("{implementation}-{version}"),
+        },
+        None => install.to_owned(),
+    };
+
+    Some(CommandVersion::new(command, &version, &path))
+}
+
+/// Conda environments are named after their install (`miniconda3`) or after
+/// the install and the environment (`miniconda3-data-science`).
+fn parse_conda_path(path: PathBuf) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    let prefix = ancestor_name(&path, 2)?;
+
+    let version = match ancestor_name(&path, 3) {
+        Some("envs") => format!("{}-{}", ancestor_name(&path, 4)?, prefix),
+        _ => prefix.to_owned(),
+    };
+
+    Some(CommandVersion::new(command, &version, &path))
+}
+
+fn conda_prefixes(warnings: &mut Vec<ScanWarning>) -> Vec<PathBuf> {
+    let home = match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home),
+        None => return vec![],
+    };
+
+    let mut prefixes = vec![];
+    for install_dir in CONDA_INSTALL_DIRS {
+        let install = home.join(install_dir);
+        prefixes.extend(glob_under(&install, "envs/*", warnings));
+        prefixes.push(install);
+    }
+
+    // conda keeps track of every environment it creates, even those created
+    // outside of the usual install directories.
+    if let Ok(contents) = fs::read_to_string(home.join(CONDA_ENVIRONMENTS_FILE)) {
+        prefixes.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from),
+        );
+    }
+
+    prefixes
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let pyenv = tool_dir("PYENV_ROOT", ".pyenv")
+        .map(|root| glob_under(&root, "versions/*/bin/*", warnings))
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(parse_pyenv_path);
+
+    let uv = tool_dir("UV_PYTHON_INSTALL_DIR", ".local/share/uv/python")
+        .map(|root| glob_under(&root, "*/bin/*", warnings))
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(parse_uv_path);
+
+    let conda = conda_prefixes(warnings)
+        .into_iter()
+        .flat_map(|prefix| glob_under(&prefix, "bin/*", warnings))
+        .flat_map(parse_conda_path);
+
+    let mut versions: Vec<_> = pyenv.chain(uv).chain(conda).collect();
+
+    // Conda environments can be found both in their install directory and in
+    // the environments file.
+    versions.sort();
+    versions.dedup();
+
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pyenv_path_python() {
+        assert_eq!(
+            parse_pyenv_path(PathBuf::from("/home/me/.pyenv/versions/3.11.7/bin/python")),
+            Some(CommandVersion::new(
+                "python",
+                "3.11.7",
+                Path::new("/home/me/.pyenv/versions/3.11.7/bin/python"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_pyenv_path_conda() {
+        assert_eq!(
+            parse_pyenv_path(PathBuf::from(
+                "/home/me/.pyenv/versions/miniconda3-latest/bin/python"
+            )),
+            Some(CommandVersion::new(
+                "python",
+                "miniconda3-latest",
+                Path::new("/home/me/.pyenv/versions/miniconda3-latest/bin/python"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_uv_path_cpython() {
+        assert_eq!(
+            parse_uv_path(PathBuf::from(
+                "/home/me/.local/share/uv/python/cpython-3.11.7-linux-x86_64-gnu/bin/python3"
+            )),
+            Some(CommandVersion::new(
+                "python3",
+                "3.11.7",
+                Path::new(
+                    "/home/me/.local/share/uv/python/cpython-3.11.7-linux-x86_64-gnu/bin/python3"
+                ),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_uv_path_pypy() {
+        assert_eq!(
+            parse_uv_path(PathBuf::from(
+                "/home/me/.local/share/uv/python/pypy-3.10.14-macos-aarch64-none/bin/python3"
+            )),
+            Some(CommandVersion::new(
+                "python3",
+                "pypy-3.10.14",
+                Path::new(
+                    "/home/me/.local/share/uv/python/pypy-3.10.14-macos-aarch64-none/bin/python3"
+                ),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_uv_path_unknown_layout() {
+        assert_eq!(
+            parse_uv_path(PathBuf::from("/opt/uv/python/custom/bin/python3")),
+            Some(CommandVersion::new(
+                "python3",
+                "custom",
+                Path::new("/opt/uv/python/custom/bin/python3"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_conda_path_base() {
+        assert_eq!(
+            parse_conda_path(PathBuf::from("/home/me/miniconda3/bin/python")),
+            Some(CommandVersion::new(
+                "python",
+                "miniconda3",
+                Path::new("/home/me/miniconda3/bin/python"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_conda_path_env() {
+        assert_eq!(
+            parse_conda_path(PathBuf::from("/home/me/miniconda3/envs/data/bin/python")),
+            Some(CommandVersion::new(
+                "python",
+                "miniconda3-data",
+                Path::new("/home/me/miniconda3/envs/data/bin/python"),
+            ))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use super::{ancestor_name, glob_under, tool_dir, ScanWarning};
+use crate::command_version::CommandVersion;
+use std::env;
+use std::path::*;
+
+const CHRUBY_USER_DIR: &str = ".rubies";
+const CHRUBY_SYSTEM_DIR: &str = "/opt/rubies";
+
+/// Every binary in a ruby install is reported (`ruby`, `gem`, `bundle`, `irb`,
+/// ...) so that the companion commands of a version can be scanned for as
+/// well.
+fn parse_version_path(path: PathBuf) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    let install = ancestor_name(&path, 2)?;
+
+    // ruby-install & chruby name installs `ruby-3.2.2` while rbenv uses
+    // `3.2.2`. Other implementations (`jruby-9.4.5.0`) are kept as is.
+    let version = install.strip_prefix("ruby-").unwrap_or(install);
+
+    Some(CommandVersion::new(command, version, &path))
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    let rbenv = tool_dir("RBENV_ROOT", ".rbenv")
+        .map(|root| glob_under(&root, "versions/*/bin/*", warnings))
+        .unwrap_or_default();
+
+    let chruby_user = env::var_os("HOME")
+        .map(|home| glob_under(&Path::new(&home).join(CHRUBY_USER_DIR), "*/bin/*", warnings))
+        .unwrap_or_default();
+
+    let chruby_system = glob_under(Path::new(CHRUBY_SYSTEM_DIR), "*/bin/*", warnings);
+
+    rbenv
+        .into_iter()
+        .chain(chruby_user)
+        .chain(chruby_system)
+        .flat_map(parse_version_path)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rbenv_path_ruby() {
+        assert_eq!(
+            parse_version_path(PathBuf::from("/home/me/.rbenv/versions/3.2.2/bin/ruby")),
+            Some(CommandVersion::new(
+                "ruby",
+                "3.2.2",
+                Path::new("/home/me/.rbenv/versions/3.2.2/bin/ruby"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_rbenv_path_gem() {
+        assert_eq!(
+            parse_version_path(PathBuf::from("/home/me/.rbenv/versions/3.1.4/bin/gem")),
+            Some(CommandVersion::new(
+                "gem",
+                "3.1.4",
+                Path::new("/home/me/.rbenv/versions/3.1.4/bin/gem"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_chruby_path_bundle() {
+        assert_eq!(
+            parse_version_path(PathBuf::from("/home/me/.rubies/ruby-3.3.0/bin/bundle")),
+            Some(CommandVersion::new(
+                "bundle",
+                "3.3.0",
+                Path::new("/home/me/.rubies/ruby-3.3.0/bin/bundle"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_opt_rubies_path_irb() {
+        assert_eq!(
+            parse_version_path(PathBuf::from("/opt/rubies/ruby-2.7.8/bin/irb")),
+            Some(CommandVersion::new(
+                "irb",
+                "2.7.8",
+                Path::new("/opt/rubies/ruby-2.7.8/bin/irb"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_opt_rubies_path_jruby() {
+        assert_eq!(
+            parse_version_path(PathBuf::from("/opt/rubies/jruby-9.4.5.0/bin/ruby")),
+            Some(CommandVersion::new(
+                "ruby",
+                "jruby-9.4.5.0",
+                Path::new("/opt/rubies/jruby-9.4.5.0/bin/ruby"),
+            ))
+        );
+    }
+}
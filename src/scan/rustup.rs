@@ -0,0 +1,117 @@
+use super::{ancestor_name, glob_under, tool_dir, ScanWarning};
+use crate::command_version::CommandVersion;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::*;
+
+lazy_static! {
+    static ref TOOLCHAIN_REGEX: Regex = Regex::new(
+        r"^(?P<channel>stable|beta|nightly|\d+\.\d+(?:\.\d+)?)(?P<date>-\d{4}-\d{2}-\d{2})?-(?P<host>.+)$"
+    )
+    .unwrap();
+}
+
+/// Toolchains are named `<channel>[-<date>]-<host triple>`. The host triple is
+/// dropped since it's the same for almost every toolchain. Custom toolchains
+/// (`rustup toolchain link`) are kept as is.
+fn toolchain_version_name(toolchain: &str) -> String {
+    match TOOLCHAIN_REGEX.captures(toolchain) {
+        Some(captures) => format!(
+            "{}{}",
+            &captures["channel"],
+            captures.name("date").map_or("", |date| date.as_str())
+        ),
+        None => toolchain.to_owned(),
+    }
+}
+
+fn parse_version_path(path: PathBuf) -> Option<CommandVersion> {
+    let command = ancestor_name(&path, 0)?;
+    let toolchain = ancestor_name(&path, 2)?;
+
+    Some(CommandVersion::new(
+        command,
+        &toolchain_version_name(toolchain),
+        &path,
+    ))
+}
+
+pub fn scan(warnings: &mut Vec<ScanWarning>) -> Vec<CommandVersion> {
+    tool_dir("RUSTUP_HOME", ".rustup")
+        .map(|root| glob_under(&root, "toolchains/*/bin/*", warnings))
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(parse_version_path)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stable_path_cargo() {
+        assert_eq!(
+            parse_version_path(PathBuf::from(
+                "/home/me/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/bin/cargo"
+            )),
+            Some(CommandVersion::new(
+                "cargo",
+                "stable",
+                Path::new("/home/me/.rustup/toolchains/stable-x86_64-unknown-linux-gnu/bin/cargo"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_numbered_path_rustc() {
+        assert_eq!(
+            parse_version_path(PathBuf::from(
+                "/home/me/.rustup/toolchains/1.75.0-aarch64-apple-darwin/bin/rustc"
+            )),
+            Some(CommandVersion::new(
+                "rustc",
+                "1.75.0",
+                Path::new("/home/me/.rustup/toolchains/1.75.0-aarch64-apple-darwin/bin/rustc"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_dated_nightly_path_rustfmt() {
+        assert_eq!(
+            parse_version_path(PathBuf::from(
+                "/home/me/.rustup/toolchains/nightly-2024-01-01-x86_64-unknown-linux-gnu/bin/rustfmt"
+            )),
+            Some(CommandVersion::new(
+                "rustfmt",
+                "nightly-2024-01-01",
+                Path::new(
+                    "/home/me/.rustup/toolchains/nightly-2024-01-01-x86_64-unknown-linux-gnu/bin/rustfmt"
+                ),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_custom_toolchain_path_clippy_driver() {
+        assert_eq!(
+            parse_version_path(PathBuf::from(
+                "/home/me/.rustup/toolchains/my-toolchain/bin/clippy-driver"
+            )),
+            Some(CommandVersion::new(
+                "clippy-driver",
+                "my-toolchain",
+                Path::new("/home/me/.rustup/toolchains/my-toolchain/bin/clippy-driver"),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_toolchain_version_name_short_version() {
+        assert_eq!(
+            toolchain_version_name("1.75-x86_64-unknown-linux-gnu"),
+            "1.75"
+        );
+    }
+}
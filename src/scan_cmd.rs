@@ -1,21 +1,107 @@
-use crate::command_version::CommandVersion;
-use crate::environment::load_command_version_registry;
-use crate::scan;
+use crate::command_version::{CommandVersion, CommandVersionRegistry, Layer};
+use crate::config::ProbeConfig;
+use crate::environment::{self, load_command_version_registry, load_config, lock_home};
+use crate::probe;
+use crate::scan::{self, ScanWarning};
 use crate::shim;
+use crate::use_file;
 use anyhow::Context;
-use dialoguer::MultiSelect;
+use console::style;
+use dialoguer::{Confirm, MultiSelect};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::env;
+use std::path::{Path, PathBuf};
 use std::process;
 
-fn prompt_versions(versions: &[CommandVersion]) -> Vec<usize> {
-    let items: Vec<_> = versions
+/// Exit code used when the scan did not find anything. This lets scripts tell
+/// the difference between "nothing to do" and an actual error.
+pub const NOTHING_FOUND_EXIT_CODE: i32 = 3;
+
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+pub struct ScanOptions {
+    /// Accept every new version without prompting
+    pub yes: bool,
+    /// Only keep the versions whose name matches this regex
+    pub filter: Option<Regex>,
+    /// Print the versions that would be added instead of adding them
+    pub dry_run: bool,
+    /// Run the versions found to get their precise version
+    pub probe: bool,
+    /// Reconcile the registry with the versions found instead of prompting
+    /// for every version
+    pub update: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Serialize)]
+struct Candidate<'a> {
+    command: &'a str,
+    version: &'a str,
+    path: &'a Path,
+}
+
+fn command_width<'a>(versions: impl IntoIterator<Item = &'a CommandVersion>) -> usize {
+    versions
+        .into_iter()
+        .map(|version| version.command_name.chars().count())
+        .max()
+        .unwrap_or_default()
+}
+
+/// A line in the scan prompt. Versions that are already registered are shown
+/// alongside the new ones so that they can be removed.
+struct Choice {
+    version: CommandVersion,
+    is_registered: bool,
+    /// Name the version was found under when its name was suggested by
+    /// probing it
+    found_as: Option<String>,
+    /// A more precise name was suggested for this version
+    has_suggestion: bool,
+}
+
+impl Choice {
+    fn new(version: CommandVersion, is_registered: bool) -> Self {
+        Choice {
+            version,
+            is_registered,
+            found_as: None,
+            has_suggestion: false,
+        }
+    }
+
+    /// Picked when accepting everything that was found: new versions under
+    /// their suggested name if they have one
+    fn is_preferred(&self) -> bool {
+        !self.has_suggestion
+    }
+}
+
+fn prompt_choices(choices: &[Choice], defaults: &[bool]) -> Vec<usize> {
+    // Versions are sorted by command which groups them together in the prompt.
+    // Aligning the command names makes those groups easier to read.
+    let command_width = command_width(choices.iter().map(|choice| &choice.version));
+
+    let items: Vec<_> = choices
         .iter()
-        .map(|version| {
+        .map(|choice| {
+            let note = match (&choice.found_as, choice.is_registered) {
+                (Some(found_as), _) => format!(" (probed, found as {found_as})"),
+                (None, true) => " (registered)".to_owned(),
+                (None, false) => String::new(),
+            };
             format!(
-                "{} {} ({})",
-                version.command_name,
-                version.version_name,
-                version.path.to_str().unwrap()
+                "{:command_width$} {} ({}){}",
+                choice.version.command_name,
+                choice.version.version_name,
+                choice.version.path.display(),
+                note
             )
         })
         .collect();
@@ -28,42 +114,580 @@ fn prompt_versions(versions: &[CommandVersion]) -> Vec<usize> {
 
     MultiSelect::new()
         .items(items.as_slice())
+        .defaults(defaults)
         .clear(false)
         .interact()
-        .unwrap()
+        .expect(
+            "Failed to prompt for versions. \
+                If you're trying to use this command non-interactively, \
+                try passing in --yes or --dry-run",
+        )
+}
+
+fn print_versions(versions: &[&CommandVersion], format: &OutputFormat) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Human => {
+            let command_width = command_width(versions.iter().copied());
+
+            for version in versions {
+                println!(
+                    "{:command_width$} {} ({})",
+                    version.command_name,
+                    version.version_name,
+                    version.path.display()
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let candidates: Vec<_> = versions
+                .iter()
+                .map(|version| Candidate {
+                    command: &version.command_name,
+                    version: &version.version_name,
+                    path: &version.path,
+                })
+                .collect();
+
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&candidates)
+                    .context("failed to serialize versions to JSON")?
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// A change to the registry proposed by `alt scan --update`
+enum Change {
+    Add(CommandVersion),
+    /// The binary of a registered version no longer exists
+    Remove(CommandVersion),
+    /// The binary of a registered version no longer exists but the same
+    /// version was found somewhere else (ex: after a homebrew upgrade)
+    Repoint(CommandVersion, PathBuf),
+}
+
+#[derive(Serialize)]
+struct ChangeEntry<'a> {
+    change: &'a str,
+    command: &'a str,
+    version: &'a str,
+    path: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_path: Option<&'a Path>,
+}
+
+impl Change {
+    fn version(&self) -> &CommandVersion {
+        match self {
+            Change::Add(version) | Change::Remove(version) | Change::Repoint(version, _) => version,
+        }
+    }
+
+    fn to_entry(&self) -> ChangeEntry<'_> {
+        let version = self.version();
+        let (change, path, previous_path) = match self {
+            Change::Add(_) => ("add", version.path.as_path(), None),
+            Change::Remove(_) => ("remove", version.path.as_path(), None),
+            Change::Repoint(_, path) => ("repoint", path.as_path(), Some(version.path.as_path())),
+        };
+
+        ChangeEntry {
+            change,
+            command: &version.command_name,
+            version: &version.version_name,
+            path,
+            previous_path,
+        }
+    }
+}
+
+/// Compare the registered versions with the versions that were found. Versions
+/// whose binary still exists are left alone and so are the versions that
+/// `is_fixable` rejects.
+fn plan_update(
+    found: &[CommandVersion],
+    registered: &[CommandVersion],
+    is_fixable: impl Fn(&CommandVersion) -> bool,
+) -> Vec<Change> {
+    let same_name = |a: &CommandVersion, b: &CommandVersion| {
+        a.command_name == b.command_name && a.version_name == b.version_name
+    };
+
+    let mut changes: Vec<_> = registered
+        .iter()
+        .filter(|version| !version.path.exists() && is_fixable(version))
+        .map(
+            |version| match found.iter().find(|found| same_name(found, version)) {
+                Some(found) => Change::Repoint(version.clone(), found.path.clone()),
+                None => Change::Remove(version.clone()),
+            },
+        )
+        .collect();
+
+    // A version with the same name as a registered one is either handled
+    // above or points to a different binary that the user didn't pick.
+    changes.extend(
+        found
+            .iter()
+            .filter(|version| {
+                !registered
+                    .iter()
+                    .any(|r| same_name(r, version) || is_same_binary(r, version))
+            })
+            .map(|version| Change::Add(version.clone())),
+    );
+
+    changes.sort_by(|a, b| a.version().cmp(b.version()));
+    changes
+}
+
+fn print_changes(changes: &[Change], format: &OutputFormat) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Human => {
+            let command_width = command_width(changes.iter().map(Change::version));
+
+            for change in changes {
+                let version = change.version();
+                let line = match change {
+                    Change::Add(_) => style(format!(
+                        "+ {:command_width$} {} ({})",
+                        version.command_name,
+                        version.version_name,
+                        version.path.display()
+                    ))
+                    .green(),
+                    Change::Remove(_) => style(format!(
+                        "- {:command_width$} {} ({})",
+                        version.command_name,
+                        version.version_name,
+                        version.path.display()
+                    ))
+                    .red(),
+                    Change::Repoint(_, path) => style(format!(
+                        "~ {:command_width$} {} ({} -> {})",
+                        version.command_name,
+                        version.version_name,
+                        version.path.display(),
+                        path.display()
+                    ))
+                    .yellow(),
+                };
+                println!("{line}");
+            }
+        }
+        OutputFormat::Json => {
+            let entries: Vec<_> = changes.iter().map(Change::to_entry).collect();
+
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&entries)
+                    .context("failed to serialize changes to JSON")?
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Bring the registry up to date with the versions that were found: add the
+/// new versions, remove the ones that were uninstalled and re-point the ones
+/// that moved.
+fn update(
+    command: Option<&str>,
+    versions: &[CommandVersion],
+    found: &Found,
+    is_wanted: impl Fn(&CommandVersion) -> bool,
+    options: &ScanOptions,
+) -> anyhow::Result<()> {
+    let command_version_registry = load_command_version_registry()?;
+
+    let registered: Vec<_> = command_version_registry.iter().filter(is_wanted).collect();
+    // The changes are confirmed as a whole, suggested names are used as is
+    let suggestions = suggest_names(versions, &registered, &found.probed);
+    let versions: Vec<_> = versions
+        .iter()
+        .zip(suggestions)
+        .map(|(version, suggestion)| suggestion.unwrap_or_else(|| version.clone()))
+        .collect();
+    let mut changes = plan_update(&versions, &registered, |version| {
+        is_user_version(&command_version_registry, version)
+    });
+
+    // Like a regular scan, scanning for everything only adds the commands
+    // worth adding
+    if command.is_none() {
+        let worth_adding = commands_worth_adding(&versions, &command_version_registry)?;
+        changes.retain(|change| match change {
+            Change::Add(version) => worth_adding.contains(&version.command_name),
+            _ => true,
+        });
+    }
+
+    for version in &registered {
+        if !version.path.exists() && !is_user_version(&command_version_registry, version) {
+            print_not_fixable(&command_version_registry, version);
+        }
+    }
+
+    if changes.is_empty() {
+        match options.format {
+            OutputFormat::Json if options.dry_run => println!("[]"),
+            _ => println!("Everything is up to date."),
+        }
+        return Ok(());
+    }
+
+    print_changes(&changes, &options.format)?;
+
+    if options.dry_run {
+        return Ok(());
+    }
+
+    let should_apply = options.yes
+        || Confirm::new()
+            .with_prompt("Would you like to apply these changes?")
+            .interact()
+            .expect(
+                "Failed to prompt for changes. \
+                    If you're trying to use this command non-interactively, \
+                    try passing in --yes or --dry-run",
+            );
+    if !should_apply {
+        return Ok(());
+    }
+
+    // The home is only locked once the user made up their mind so that other
+    // alt commands don't wait on the prompt
+    let _lock = lock_home()?;
+    let mut command_version_registry = load_command_version_registry()?;
+    let mut added = vec![];
+
+    for change in changes {
+        match change {
+            Change::Add(version) => {
+                found.add_to(&mut command_version_registry, &version)?;
+                added.push(version);
+            }
+            Change::Remove(version) => {
+                command_version_registry.remove(&version.command_name, &version.version_name);
+            }
+            Change::Repoint(version, path) => {
+                let version =
+                    CommandVersion::new(&version.command_name, &version.version_name, &path);
+                found.add_to(&mut command_version_registry, &version)?;
+                added.push(version);
+            }
+        }
+    }
+
+    command_version_registry
+        .save()
+        .context("Failed to save command version registry")?;
+
+    make_shims(&added);
+
+    Ok(())
+}
+
+/// What we learned about the versions that were found, by path. This ends up
+/// in the metadata of the versions that are added.
+#[derive(Default)]
+struct Found {
+    sources: HashMap<PathBuf, &'static str>,
+    /// Versions printed by the binaries that were probed
+    probed: HashMap<PathBuf, String>,
 }
 
-pub fn run(command: &str) -> anyhow::Result<()> {
-    let versions: Vec<_> = scan::path_suffix::scan(command)
+impl Found {
+    fn add_to(
+        &self,
+        registry: &mut CommandVersionRegistry,
+        version: &CommandVersion,
+    ) -> anyhow::Result<()> {
+        let entry = registry.add(version.clone())?;
+
+        if let Some(source) = self.sources.get(&version.path) {
+            entry.source = Some(source.to_string());
+        }
+        if let Some(probed_version) = self.probed.get(&version.path) {
+            entry.probed_version = Some(probed_version.clone());
+        }
+
+        Ok(())
+    }
+}
+
+/// Probe every version to get the version printed by its binary
+fn probe_versions(versions: &[CommandVersion], config: &ProbeConfig) -> HashMap<PathBuf, String> {
+    versions
+        .iter()
+        .filter_map(|version| {
+            let probed_version = probe::probe(&version.command_name, &version.path, config)?;
+            Some((version.path.clone(), probed_version))
+        })
+        .collect()
+}
+
+/// Suggest a name for the versions whose name is less precise than their
+/// probed version (`python 3` -> `python 3.11.7`) unless that name is already
+/// taken by another version or by a `registered` one.
+fn suggest_names(
+    versions: &[CommandVersion],
+    registered: &[CommandVersion],
+    probed: &HashMap<PathBuf, String>,
+) -> Vec<Option<CommandVersion>> {
+    let mut taken: HashSet<_> = versions
+        .iter()
+        .chain(registered)
+        .map(|version| (version.command_name.clone(), version.version_name.clone()))
+        .collect();
+
+    versions
+        .iter()
+        .map(|version| {
+            let probed_version = probed.get(&version.path)?;
+            let is_suggested = probe::is_more_precise(&version.version_name, probed_version)
+                && taken.insert((version.command_name.clone(), probed_version.clone()));
+            is_suggested
+                .then(|| CommandVersion::new(&version.command_name, probed_version, &version.path))
+        })
+        .collect()
+}
+
+fn make_shims(added: &[CommandVersion]) {
+    let commands: BTreeSet<_> = added.iter().map(|version| &version.command_name).collect();
+
+    for command in commands {
+        shim::make_shim(command, env::current_exe().unwrap().as_path())
+            .unwrap_or_else(|err| panic!("failed to create shim for {command}: {err}"));
+    }
+}
+
+/// Warnings go to stderr so that they don't end up in the JSON output.
+fn print_warnings(warnings: &[ScanWarning]) {
+    for warning in warnings {
+        eprintln!("{}: {warning}", style("Warning").bold().yellow());
+    }
+}
+
+/// Commands worth adding when scanning for everything. Scanners find every
+/// binary of a JDK, a conda environment, ... and most of them never need to be
+/// switched. Only the commands that are already defined, that are asked for by
+/// the use files of the current directory or that were found in more than one
+/// version are worth adding.
+fn commands_worth_adding(
+    found: &[CommandVersion],
+    registry: &CommandVersionRegistry,
+) -> anyhow::Result<HashSet<String>> {
+    let mut version_counts: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for version in found {
+        version_counts
+            .entry(&version.command_name)
+            .or_default()
+            .insert(&version.version_name);
+    }
+
+    let mut commands: HashSet<_> = version_counts
         .into_iter()
-        .chain(scan::homebrew::scan(command))
+        .filter(|(_, versions)| versions.len() > 1)
+        .map(|(command, _)| command.to_owned())
         .collect();
+    commands.extend(registry.command_names());
+
+    // Broken use files are reported by the commands that use them
+    let use_files = use_file::load_all(use_file::find(&env::current_dir()?)).unwrap_or_default();
+    for (_, file) in use_files {
+        commands.extend(file.versions.into_keys());
+    }
+
+    Ok(commands)
+}
+
+/// Only the user's own definitions can be changed by a scan
+fn is_user_version(registry: &CommandVersionRegistry, version: &CommandVersion) -> bool {
+    registry.layer(&version.command_name, &version.version_name) == Some(&Layer::User)
+}
+
+fn print_not_fixable(registry: &CommandVersionRegistry, version: &CommandVersion) {
+    if let Some(layer) = registry.layer(&version.command_name, &version.version_name) {
+        eprintln!(
+            "{}: {} is defined in {}, can't be fixed here.",
+            style("Warning").bold().yellow(),
+            version,
+            environment::layer_file(layer).display()
+        );
+    }
+}
+
+fn is_same_binary(a: &CommandVersion, b: &CommandVersion) -> bool {
+    a.command_name == b.command_name
+        && (a.path == b.path || scan::canonical_path(&a.path) == scan::canonical_path(&b.path))
+}
+
+/// Scan for versions of `command` or for versions of every command when
+/// `command` is `None`.
+pub fn run(command: Option<&str>, options: &ScanOptions) -> anyhow::Result<()> {
+    let is_wanted = |version: &CommandVersion| {
+        command.is_none_or(|command| version.command_name == command)
+            && options
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.is_match(&version.version_name))
+    };
+
+    let report = scan::scan(command);
+    let versions: Vec<_> = report.versions.into_iter().filter(is_wanted).collect();
+
+    let mut found = Found {
+        sources: report.sources,
+        ..Found::default()
+    };
+    if options.probe {
+        found.probed = probe_versions(&versions, &load_config()?.probe);
+    }
+
+    // Finding nothing is expected when every version was uninstalled.
+    if options.update {
+        update(command, &versions, &found, is_wanted, options)?;
+        print_warnings(&report.warnings);
+        return Ok(());
+    }
 
     if versions.is_empty() {
-        println!("Sorry, could not find any versions of {command}");
-        process::exit(1);
-    } else {
-        let choices = prompt_versions(&versions);
+        match (&options.format, command) {
+            (OutputFormat::Json, _) if options.dry_run => println!("[]"),
+            (_, Some(command)) => println!("Sorry, could not find any versions of {command}"),
+            (_, None) => println!("Sorry, could not find any versioned commands"),
+        }
+        print_warnings(&report.warnings);
+        process::exit(NOTHING_FOUND_EXIT_CODE);
+    }
 
-        if choices.is_empty() {
-            println!("Looks like you didn't choose anything.");
-            println!("Did you forget to select versions with <space>?");
+    let command_version_registry = load_command_version_registry()?;
+
+    let registered: Vec<_> = command_version_registry.iter().filter(is_wanted).collect();
+    let worth_adding = commands_worth_adding(&versions, &command_version_registry)?;
+    let is_worth_adding =
+        |choice: &Choice| command.is_some() || worth_adding.contains(&choice.version.command_name);
+
+    // Versions that point to an already registered binary are not new. They
+    // are represented by their registry entry.
+    let new_versions: Vec<_> = versions
+        .into_iter()
+        .filter(|version| !registered.iter().any(|r| is_same_binary(r, version)))
+        .collect();
+
+    // Names suggested by probing are offered next to the names the versions
+    // were found under
+    let suggestions = suggest_names(&new_versions, &registered, &found.probed);
+    let mut choices = vec![];
+    for (version, suggestion) in new_versions.into_iter().zip(suggestions) {
+        if let Some(suggestion) = suggestion {
+            choices.push(Choice {
+                found_as: Some(version.version_name.clone()),
+                ..Choice::new(suggestion, false)
+            });
+            choices.push(Choice {
+                has_suggestion: true,
+                ..Choice::new(version, false)
+            });
         } else {
-            let mut command_version_registry = load_command_version_registry()?;
+            choices.push(Choice::new(version, false));
+        }
+    }
+    // Versions defined in other files can't be removed from here
+    choices.extend(
+        registered
+            .into_iter()
+            .filter(|version| is_user_version(&command_version_registry, version))
+            .map(|version| Choice::new(version, true)),
+    );
+    choices.sort_by(|a, b| a.version.cmp(&b.version));
 
-            for choice in choices {
-                let version = versions[choice].clone();
-                command_version_registry.add(version)?;
-            }
+    if options.dry_run {
+        let to_add: Vec<_> = choices
+            .iter()
+            .filter(|choice| {
+                !choice.is_registered && choice.is_preferred() && is_worth_adding(choice)
+            })
+            .map(|choice| &choice.version)
+            .collect();
+        print_versions(&to_add, &options.format)?;
+        print_warnings(&report.warnings);
+        return Ok(());
+    }
+
+    let selected: Vec<_> = if options.yes {
+        choices
+            .iter()
+            .map(|choice| {
+                choice.is_registered || (choice.is_preferred() && is_worth_adding(choice))
+            })
+            .collect()
+    } else {
+        // Registered versions are checked so that unchecking them removes
+        // them. When scanning for everything, we assume that the user wants
+        // the new versions of the commands worth adding.
+        let defaults: Vec<_> = choices
+            .iter()
+            .map(|choice| {
+                choice.is_registered
+                    || (command.is_none() && choice.is_preferred() && is_worth_adding(choice))
+            })
+            .collect();
+
+        let mut selected = vec![false; choices.len()];
+        for i in prompt_choices(&choices, &defaults) {
+            selected[i] = true;
+        }
+        selected
+    };
 
-            command_version_registry
-                .save()
-                .context("Failed to save command version registry")?;
+    // Like with --update, the home is only locked after the prompt
+    let _lock = lock_home()?;
+    let mut command_version_registry = load_command_version_registry()?;
+    let mut added = vec![];
+    let mut change_count = 0;
 
-            shim::make_shim(command, env::current_exe().unwrap().as_path())
-                .unwrap_or_else(|err| panic!("failed to create shim for {command}: {err}"));
+    for (choice, is_selected) in choices.into_iter().zip(selected) {
+        match (choice.is_registered, is_selected) {
+            (false, true) => {
+                if options.yes {
+                    println!("Adding {}", choice.version);
+                }
+                found.add_to(&mut command_version_registry, &choice.version)?;
+                added.push(choice.version);
+                change_count += 1;
+            }
+            (true, false) => {
+                println!("Removing {}", choice.version);
+                command_version_registry
+                    .remove(&choice.version.command_name, &choice.version.version_name);
+                change_count += 1;
+            }
+            _ => {}
+        }
+    }
+
+    if change_count == 0 {
+        if options.yes {
+            println!("All the versions I found are already defined.");
+        } else {
+            println!("Looks like you didn't change anything.");
+            println!("Did you forget to select versions with <space>?");
         }
+    } else {
+        command_version_registry
+            .save()
+            .context("Failed to save command version registry")?;
+
+        make_shims(&added);
     }
 
+    print_warnings(&report.warnings);
+
     Ok(())
 }
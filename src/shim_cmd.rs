@@ -1,15 +1,30 @@
-use crate::environment::load_command_version_registry;
+use crate::environment::{load_command_version_registry, load_trust_store};
 use crate::shim;
+use crate::use_file;
 use anyhow::Context;
 use console::style;
+use std::collections::BTreeSet;
 use std::env;
 
 pub fn run() -> anyhow::Result<()> {
-    shim::empty_shim_dir().context("failed to empty shim dir")?;
-
     let command_version_registry = load_command_version_registry()?;
+    let mut commands: BTreeSet<_> = command_version_registry.command_names().collect();
+
+    // Commands that are only defined by projects need shims too. The shims
+    // are made for every trusted project, not just the current one, since
+    // the shim dir is emptied.
+    let trust_store = load_trust_store()?;
+    for path in trust_store.paths() {
+        if let Ok(Some(file)) = use_file::load(path) {
+            if trust_store.is_trusted(path, &file.digest) {
+                commands.extend(file.defs.into_keys());
+            }
+        }
+    }
+
+    shim::empty_shim_dir().context("failed to empty shim dir")?;
 
-    for command in command_version_registry.command_names() {
+    for command in commands {
         let res = shim::make_shim(&command, &env::current_exe().unwrap());
         match res {
             Ok(()) => println!(" {} {}", style("✓").green().bold(), command),
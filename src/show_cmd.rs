@@ -1,3 +1,5 @@
+use crate::command::{add_all_project_defs, find_selected_version};
+use crate::command_version::Layer;
 use crate::environment::load_command_version_registry;
 use crate::use_file;
 use console::style;
@@ -5,7 +7,10 @@ use std::env;
 use std::process;
 
 pub fn run() -> anyhow::Result<()> {
-    let registry = load_command_version_registry()?;
+    let mut registry = load_command_version_registry()?;
+
+    let use_files = use_file::load_all(use_file::find(&env::current_dir().unwrap()))?;
+    add_all_project_defs(&mut registry, &use_files)?;
 
     if registry.is_empty() {
         println!("No commands are defined.");
@@ -13,48 +18,64 @@ pub fn run() -> anyhow::Result<()> {
         process::exit(1);
     }
 
-    let use_file = use_file::find(&env::current_dir().unwrap());
-    let used_versions = use_file
-        .as_ref()
-        .and_then(|path| use_file::load(path))
-        .unwrap_or_default();
-
-    if let Some(use_file_path) = use_file {
-        println!("Versions from: {}", use_file_path.to_str().unwrap());
+    if !use_files.is_empty() {
+        let paths = use_files
+            .iter()
+            .map(|(path, _)| path.display().to_string())
+            .collect::<Vec<_>>();
+        println!("Versions from: {}", paths.join(", "));
     }
 
-    let mut command_versions = registry.iter().collect::<Vec<_>>();
-    command_versions.sort_by(|a, b| {
-        (&a.command_name, &a.version_name).cmp(&(&b.command_name, &b.version_name))
-    });
+    let mut command_versions = registry.entries().collect::<Vec<_>>();
+    command_versions.sort_by(|(a, _), (b, _)| a.cmp(b));
 
     let mut current_command: Option<String> = None;
-    for command_version in command_versions {
-        let currently_used_version = used_versions.get(&command_version.command_name);
+    for (command_version, entry) in command_versions {
+        let mut notes = String::new();
+        match registry.layer(&command_version.command_name, &command_version.version_name) {
+            Some(Layer::User) | None => {}
+            Some(layer) => notes.push_str(&format!(" {}", style(format!("[{layer}]")).cyan())),
+        }
+        if let Some(description) = &entry.description {
+            notes.push_str(&format!(" {description}"));
+        }
+        if entry.disabled {
+            notes.push_str(&format!(" {}", style("(disabled)").red()));
+        }
+
+        let currently_used_version =
+            find_selected_version(&registry, &use_files, &command_version.command_name);
 
         if current_command.as_ref() != Some(&command_version.command_name) {
             current_command = Some(command_version.command_name.clone());
 
             let command_display = style(&command_version.command_name).bold();
-            if currently_used_version.is_some() {
-                println!("{command_display}");
-            } else {
-                println!("{} {}", command_display, style("(using system)").yellow());
+            match &currently_used_version {
+                Ok(Some(_)) => println!("{command_display}"),
+                Ok(None) => println!("{} {}", command_display, style("(using system)").yellow()),
+                Err(err) => println!("{} {}", command_display, style(format!("({err})")).red()),
             }
         }
 
-        if currently_used_version == Some(&command_version.version_name) {
+        let is_used = currently_used_version
+            .as_ref()
+            .ok()
+            .and_then(Option::as_ref)
+            == Some(&command_version.version_name);
+        if is_used {
             println!(
-                " {} {} ({})",
+                " {} {} ({}){}",
                 style("*").green().bold(),
                 &command_version.version_name,
-                command_version.path.display()
+                command_version.path.display(),
+                notes
             );
         } else {
             println!(
-                "   {} ({})",
+                "   {} ({}){}",
                 &command_version.version_name,
-                command_version.path.display()
+                command_version.path.display(),
+                notes
             );
         }
     }
@@ -0,0 +1,120 @@
+use crate::parse_error::ParseError;
+use crate::safe_file;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(thiserror::Error, Debug)]
+pub enum LoadError {
+    #[error("invalid trust file {0}")]
+    ParseError(#[from] ParseError),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+/// Project files the user trusts (see `alt allow`). Files are trusted by the
+/// hash of their contents so that any change revokes the trust.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct TrustStore {
+    #[serde(default)]
+    files: BTreeMap<PathBuf, String>,
+}
+
+pub fn digest(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// Files are recorded by their real path so that the same file reached
+/// through a symlink is still trusted
+fn key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+impl TrustStore {
+    pub fn load_or_default(path: &Path) -> Result<Self, LoadError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents)
+                .map_err(|err| ParseError::new(path, &contents, &err).into()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(TrustStore::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let toml = toml::to_string_pretty(self).expect("failed to serialize trust toml");
+        safe_file::write_atomic(path, toml)
+    }
+
+    /// Check that the file at `path` is trusted with its current contents
+    pub fn is_trusted(&self, path: &Path, digest: &str) -> bool {
+        self.files
+            .get(&key(path))
+            .is_some_and(|trusted| trusted == digest)
+    }
+
+    pub fn allow(&mut self, path: &Path, digest: &str) {
+        self.files.insert(key(path), digest.to_owned());
+    }
+
+    /// Paths of the trusted files
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Returns `false` when the file wasn't trusted
+    pub fn deny(&mut self, path: &Path) -> bool {
+        self.files.remove(&key(path)).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    #[test]
+    fn trust_is_revoked_on_change() {
+        let mut store = TrustStore::default();
+        let path = Path::new("/project/.alt.toml");
+
+        store.allow(path, &digest(b"node = \"18\"\n"));
+
+        assert!(store.is_trusted(path, &digest(b"node = \"18\"\n")));
+        assert!(!store.is_trusted(path, &digest(b"node = \"20\"\n")));
+        assert!(!store.is_trusted(Path::new("/other/.alt.toml"), &digest(b"node = \"18\"\n")));
+    }
+
+    #[test]
+    fn deny_forgets_file() {
+        let mut store = TrustStore::default();
+        let path = Path::new("/project/.alt.toml");
+
+        store.allow(path, &digest(b""));
+
+        assert!(store.deny(path));
+        assert!(!store.is_trusted(path, &digest(b"")));
+        assert!(!store.deny(path));
+    }
+
+    #[test]
+    fn save_and_load() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let path = workdir.path().join("trusted.toml");
+        let mut store = TrustStore::default();
+        store.allow(Path::new("/project/.alt.toml"), &digest(b""));
+
+        store.save(&path)?;
+
+        assert_eq!(TrustStore::load_or_default(&path)?, store);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
+use crate::command::{add_all_project_defs, resolve_version, NoMatchingVersion};
 use crate::command_version::CommandVersion;
-use crate::environment::load_command_version_registry;
-use crate::use_file;
+use crate::environment::{load_command_version_registry, load_trust_store, lock_home, trust_file};
+use crate::shim;
+use crate::use_file::{self, UseFile};
+use anyhow::Context;
 use dialoguer::Select;
 use std::env;
+use std::path::Path;
 use std::process;
 
 enum SelectedVersion {
@@ -10,12 +14,25 @@ enum SelectedVersion {
     ThisOne(CommandVersion),
 }
 
-pub fn run(command: &str, arg_version: Option<&str>) -> anyhow::Result<()> {
-    let registry = load_command_version_registry()?;
+pub fn run(command: &str, arg_version: Option<&str>, shared: bool) -> anyhow::Result<()> {
+    let mut registry = load_command_version_registry()?;
+
+    // Versions defined by the project can be used too
+    let cwd = env::current_dir().unwrap();
+    let use_files = use_file::load_all(use_file::find(&cwd))?;
+    add_all_project_defs(&mut registry, &use_files)?;
+
+    let file_name = if shared {
+        use_file::SHARED_FILE_NAME
+    } else {
+        use_file::FILE_NAME
+    };
+    let use_file = use_file::find_or_dir(&cwd, file_name);
 
     let command_versions = registry
-        .iter()
-        .filter(|v| v.command_name == command)
+        .entries()
+        .filter(|(v, entry)| v.command_name == command && !entry.disabled)
+        .map(|(v, _)| v)
         .collect::<Vec<_>>();
 
     if command_versions.is_empty() {
@@ -24,25 +41,43 @@ pub fn run(command: &str, arg_version: Option<&str>) -> anyhow::Result<()> {
         process::exit(1);
     }
 
+    // Constraints are resolved to check that a version matches them but they
+    // are what ends up in the use file
     let selected_version = match arg_version {
-        Some("system") => SelectedVersion::System,
-        Some(version) => SelectedVersion::ThisOne(registry.get(command, version).unwrap()),
+        Some(requested) => match resolve_version(&registry, command, requested)? {
+            None => SelectedVersion::System,
+            Some(version) => match registry.get(command, &version) {
+                Some(version) => SelectedVersion::ThisOne(version),
+                None => {
+                    return Err(NoMatchingVersion {
+                        command: command.to_owned(),
+                        requested: requested.to_owned(),
+                    }
+                    .into())
+                }
+            },
+        },
         None => prompt_version(&command_versions),
     };
 
     match selected_version {
         SelectedVersion::System => {
-            let cwd = env::current_dir().unwrap();
-            let use_file = use_file::find_or_dir(&cwd);
-            let mut use_def = use_file::load(&use_file).unwrap_or_default();
-            use_def.remove(command);
-            use_file::save(&use_def, &use_file).unwrap_or_else(|err| {
-                panic!(
-                    "Failed to write use file to {}: {}",
-                    use_file.to_str().unwrap(),
-                    err
-                )
-            });
+            let _lock = use_file::lock(&use_file)
+                .with_context(|| format!("failed to lock {}", use_file.display()))?;
+            let mut use_def = use_file::load(&use_file)?.unwrap_or_default();
+            // The personal file has to override the shared file explicitly
+            let shared_file = use_file.with_file_name(use_file::SHARED_FILE_NAME);
+            let shared_version =
+                use_file::load(&shared_file)?.and_then(|file| file.versions.get(command).cloned());
+            if !shared && shared_version.is_some_and(|version| version != use_file::SYSTEM_VERSION)
+            {
+                use_def
+                    .versions
+                    .insert(command.to_owned(), use_file::SYSTEM_VERSION.to_owned());
+            } else {
+                use_def.versions.remove(command);
+            }
+            save(&use_def, &use_file)?;
 
             println!(
                 "Will now use system version of {} when in {}",
@@ -55,17 +90,19 @@ pub fn run(command: &str, arg_version: Option<&str>) -> anyhow::Result<()> {
             version_name,
             path,
         }) => {
-            let cwd = env::current_dir().unwrap();
-            let use_file = use_file::find_or_dir(&cwd);
-            let mut use_def = use_file::load(&use_file).unwrap_or_default();
-            use_def.insert(command_name.clone(), version_name.clone());
-            use_file::save(&use_def, &use_file).unwrap_or_else(|err| {
-                panic!(
-                    "Failed to write use file to {}: {}",
-                    use_file.to_str().unwrap(),
-                    err
-                )
-            });
+            let _lock = use_file::lock(&use_file)
+                .with_context(|| format!("failed to lock {}", use_file.display()))?;
+            let mut use_def = use_file::load(&use_file)?.unwrap_or_default();
+            let requested = arg_version.unwrap_or(&version_name);
+            use_def
+                .versions
+                .insert(command_name.clone(), requested.to_owned());
+            save(&use_def, &use_file)?;
+
+            // Commands that are only defined by the project don't have a
+            // shim yet
+            shim::make_shim(&command_name, env::current_exe().unwrap().as_path())
+                .unwrap_or_else(|err| panic!("failed to create shim for {command_name}: {err}"));
 
             println!(
                 "Will now use {} {} ({}) when in {}",
@@ -80,6 +117,29 @@ pub fn run(command: &str, arg_version: Option<&str>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Save the use file. A file that was trusted stays trusted since the changes
+/// are our own.
+fn save(use_def: &UseFile, path: &Path) -> anyhow::Result<()> {
+    let digest = use_file::save(use_def, path).unwrap_or_else(|err| {
+        panic!(
+            "Failed to write use file to {}: {}",
+            path.to_str().unwrap(),
+            err
+        )
+    });
+
+    let _lock = lock_home()?;
+    let mut trust_store = load_trust_store()?;
+    if trust_store.is_trusted(path, &use_def.digest) {
+        trust_store.allow(path, &digest);
+        trust_store
+            .save(&trust_file())
+            .context("failed to save trusted files")?;
+    }
+
+    Ok(())
+}
+
 fn prompt_version(versions: &[CommandVersion]) -> SelectedVersion {
     let mut versions = versions.to_owned();
     versions.sort();
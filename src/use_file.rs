@@ -1,37 +1,362 @@
-use std::collections::HashMap;
+use crate::parse_error::ParseError;
+use crate::safe_file::{self, DirLock};
+use crate::trust;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
 
-const FILE_NAME: &str = ".alt.toml";
+/// Personal use file written by `alt use`. It isn't meant to be committed.
+pub const FILE_NAME: &str = ".alt.toml";
+/// Use file shared by the people working on a project. It's meant to be
+/// committed.
+pub const SHARED_FILE_NAME: &str = "alt.toml";
+const DEFS_KEY: &str = "defs";
+/// Version that asks for the system version of a command. This lets the
+/// personal file override a version asked by the shared file.
+pub const SYSTEM_VERSION: &str = "system";
 
-pub fn find(start: &Path) -> Option<PathBuf> {
-    let mut dir = PathBuf::from(start);
-    loop {
-        let file = dir.join(FILE_NAME);
+/// Use files of a directory in order of precedence: the personal file
+/// overrides the shared one.
+fn dir_files(dir: &Path) -> Vec<PathBuf> {
+    [FILE_NAME, SHARED_FILE_NAME]
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|file| file.is_file())
+        .collect()
+}
+
+/// Find the use files that apply to `start`. These are the files of the
+/// closest directory that has any, in order of precedence.
+pub fn find(start: &Path) -> Vec<PathBuf> {
+    start
+        .ancestors()
+        .map(dir_files)
+        .find(|files| !files.is_empty())
+        .unwrap_or_default()
+}
+
+/// Path of the use file named `name` that applies to `start`. It goes next to
+/// the use files that already apply or in `start` when there are none.
+pub fn find_or_dir(start: &Path, name: &str) -> PathBuf {
+    let dir = find(start)
+        .first()
+        .and_then(|file| file.parent())
+        .map(Path::to_owned)
+        .unwrap_or_else(|| start.to_owned());
+    dir.join(name)
+}
+
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct UseFile {
+    /// Version to use for each command
+    #[serde(flatten)]
+    pub versions: BTreeMap<String, String>,
+    /// Versions defined by the project (`[defs.protoc] "3.21" = "tools/bin/protoc"`).
+    /// Paths are relative to the directory of the use file.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub defs: BTreeMap<String, BTreeMap<String, PathBuf>>,
+    /// Hash of the contents the file was loaded from (see [`trust::digest`])
+    #[serde(skip)]
+    pub digest: String,
+}
+
+impl UseFile {
+    /// Paths of the versions defined by the project, resolved against the
+    /// directory of the use file at `path`
+    pub fn resolved_defs(&self, path: &Path) -> BTreeMap<String, BTreeMap<String, PathBuf>> {
+        let dir = path.parent().unwrap_or(Path::new("/"));
+        self.defs
+            .iter()
+            .map(|(command, versions)| {
+                let versions = versions
+                    .iter()
+                    .map(|(version, bin)| (version.clone(), dir.join(bin)))
+                    .collect();
+                (command.clone(), versions)
+            })
+            .collect()
+    }
+}
+
+/// Deserialized by hand since `#[serde(flatten)]` loses the location of
+/// errors. This means that there can't be a command named `defs`.
+impl<'de> Deserialize<'de> for UseFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct UseFileVisitor;
+
+        impl<'de> Visitor<'de> for UseFileVisitor {
+            type Value = UseFile;
 
-        if file.is_file() {
-            return Some(file);
-        } else {
-            dir.parent()?;
-            dir.pop();
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a table of command versions")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<UseFile, A::Error> {
+                let mut use_file = UseFile::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == DEFS_KEY {
+                        use_file.defs = map.next_value()?;
+                    } else {
+                        use_file.versions.insert(key, map.next_value()?);
+                    }
+                }
+                Ok(use_file)
+            }
         }
+
+        deserializer.deserialize_map(UseFileVisitor)
     }
 }
 
-pub fn find_or_dir(start: &Path) -> PathBuf {
-    find(start).unwrap_or_else(|| start.join(FILE_NAME))
+#[derive(thiserror::Error, Debug)]
+pub enum LoadError {
+    #[error("invalid use file {0}")]
+    ParseError(#[from] ParseError),
+    #[error("failed to read {}: {err}", path.display())]
+    IoError { path: PathBuf, err: io::Error },
 }
 
-pub type UseFile = HashMap<String, String>;
+/// Load a use file. `None` is returned when there is no file at `path`.
+pub fn load(path: &Path) -> Result<Option<UseFile>, LoadError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(LoadError::IoError {
+                path: path.to_owned(),
+                err,
+            })
+        }
+    };
+
+    let mut use_file: UseFile =
+        toml::from_str(&contents).map_err(|err| ParseError::new(path, &contents, &err))?;
+    use_file.digest = trust::digest(contents.as_bytes());
+    Ok(Some(use_file))
+}
 
-pub fn load(path: &Path) -> Option<UseFile> {
-    fs::read_to_string(path)
-        .ok()
-        .map(|contents| toml::from_str(&contents).unwrap())
+/// Every use file from `start` up to the root, closest first
+pub fn find_all(start: &Path) -> Vec<PathBuf> {
+    start.ancestors().flat_map(dir_files).collect()
 }
 
-pub fn save(use_def: &UseFile, path: &Path) -> Result<(), io::Error> {
-    let toml = toml::to_string_pretty(use_def).expect("failed to serialize use toml");
-    fs::write(path, toml)
+/// Load use files that were found. Files that disappeared in the meantime are
+/// skipped.
+pub fn load_all(paths: Vec<PathBuf>) -> Result<Vec<(PathBuf, UseFile)>, LoadError> {
+    let mut files = vec![];
+    for path in paths {
+        if let Some(file) = load(&path)? {
+            files.push((path, file));
+        }
+    }
+    Ok(files)
+}
+
+/// Version asked for `command` by the first file that has one
+pub fn requested_version<'a>(files: &'a [(PathBuf, UseFile)], command: &str) -> Option<&'a str> {
+    files
+        .iter()
+        .find_map(|(_, file)| file.versions.get(command))
+        .map(String::as_str)
+}
+
+/// Lock the directory of the use file while it's being changed
+pub fn lock(path: &Path) -> Result<DirLock, io::Error> {
+    safe_file::lock_dir(path.parent().unwrap_or(Path::new("/")))
+}
+
+/// Write the use file and return the digest of its new contents. The layout of
+/// the file that's already there (order, comments, ...) is kept since shared
+/// files are committed.
+pub fn save(use_def: &UseFile, path: &Path) -> Result<String, io::Error> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    // A file that can't be parsed is replaced
+    let mut doc: DocumentMut = contents.parse().unwrap_or_default();
+    let previous: UseFile = toml::from_str(&contents).unwrap_or_default();
+    let fresh: DocumentMut = toml::to_string_pretty(use_def)
+        .expect("failed to serialize use toml")
+        .parse()
+        .expect("failed to parse serialized use toml");
+
+    let removed: Vec<_> = doc
+        .iter()
+        .map(|(key, _)| key.to_owned())
+        .filter(|key| key != DEFS_KEY && !use_def.versions.contains_key(key))
+        .collect();
+    for key in removed {
+        doc.remove(&key);
+    }
+    for (command, version) in &use_def.versions {
+        match doc.get_mut(command).and_then(|item| item.as_value_mut()) {
+            Some(value) if value.as_str() == Some(version) => {}
+            // Keep the comment after the version
+            Some(value) => {
+                let decor = value.decor().clone();
+                *value = version.into();
+                *value.decor_mut() = decor;
+            }
+            None => doc[command] = toml_edit::value(version),
+        }
+    }
+    if previous.defs != use_def.defs {
+        match fresh.get(DEFS_KEY) {
+            Some(defs) => doc[DEFS_KEY] = defs.clone(),
+            None => {
+                doc.remove(DEFS_KEY);
+            }
+        }
+    }
+
+    let toml = doc.to_string();
+    let digest = trust::digest(toml.as_bytes());
+    safe_file::write_atomic(path, toml)?;
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    type TestResult = Result<(), Box<dyn Error>>;
+
+    #[test]
+    fn load_missing_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+
+        assert!(load(&workdir.path().join(FILE_NAME))?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_invalid_file_reports_location() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let path = workdir.path().join(FILE_NAME);
+        fs::write(&path, "node = \"18\"\npython = 3.11\n")?;
+
+        match load(&path) {
+            Err(LoadError::ParseError(err)) => {
+                assert_eq!(err.path, path);
+                assert_eq!((err.line, err.column), (2, 10));
+            }
+            res => panic!("expected a parse error, got {res:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_project_defs() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let path = workdir.path().join(FILE_NAME);
+        fs::write(
+            &path,
+            "protoc = \"3.21\"\n\n[defs.protoc]\n\"3.21\" = \"tools/bin/protoc\"\n",
+        )?;
+
+        let use_file = load(&path)?.unwrap();
+
+        assert_eq!(use_file.versions["protoc"], "3.21");
+        assert_eq!(
+            use_file.resolved_defs(&path)["protoc"]["3.21"],
+            workdir.path().join("tools/bin/protoc")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_round_trips_project_defs() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let path = workdir.path().join(FILE_NAME);
+        let mut use_file = UseFile::default();
+        use_file.versions.insert("protoc".into(), "3.21".into());
+        use_file.defs.insert(
+            "protoc".into(),
+            BTreeMap::from([("3.21".into(), PathBuf::from("tools/bin/protoc"))]),
+        );
+
+        save(&use_file, &path)?;
+
+        let loaded = load(&path)?.unwrap();
+        assert_eq!(loaded.versions, use_file.versions);
+        assert_eq!(loaded.defs, use_file.defs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_keeps_layout_of_existing_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let path = workdir.path().join(SHARED_FILE_NAME);
+        fs::write(
+            &path,
+            "# Versions used by CI\npython = \"3.11\"\nnode = \"18\" # LTS\nruby = \"3.2\"\n",
+        )?;
+        let mut use_file = load(&path)?.unwrap();
+        use_file.versions.insert("node".into(), "20".into());
+        use_file.versions.remove("ruby");
+        use_file.versions.insert("go".into(), "1.22".into());
+
+        save(&use_file, &path)?;
+
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "# Versions used by CI\npython = \"3.11\"\nnode = \"20\" # LTS\ngo = \"1.22\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn personal_file_comes_before_shared_file() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let nested = workdir.path().join("a");
+        fs::create_dir_all(&nested)?;
+        fs::write(
+            workdir.path().join(SHARED_FILE_NAME),
+            "node = \"18\"\npython = \"3.11\"\n",
+        )?;
+        fs::write(workdir.path().join(FILE_NAME), "node = \"20\"\n")?;
+
+        let files = load_all(find(&nested))?;
+
+        assert_eq!(files[0].0, workdir.path().join(FILE_NAME));
+        assert_eq!(files[1].0, workdir.path().join(SHARED_FILE_NAME));
+        assert_eq!(requested_version(&files, "node"), Some("20"));
+        assert_eq!(requested_version(&files, "python"), Some("3.11"));
+        assert_eq!(requested_version(&files, "ruby"), None);
+        assert_eq!(
+            find_or_dir(&nested, FILE_NAME),
+            workdir.path().join(FILE_NAME)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_all_lists_closest_first() -> TestResult {
+        let workdir = tempfile::tempdir()?;
+        let nested = workdir.path().join("a/b");
+        fs::create_dir_all(&nested)?;
+        fs::write(workdir.path().join(FILE_NAME), "")?;
+        fs::write(workdir.path().join("a/b").join(FILE_NAME), "")?;
+
+        let found = find_all(&nested);
+
+        assert_eq!(found[0], nested.join(FILE_NAME));
+        assert_eq!(found[1], workdir.path().join(FILE_NAME));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,216 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::cmp::Ordering;
+
+lazy_static! {
+    static ref NUMBERED_REGEX: Regex =
+        Regex::new(r"^v?(?P<numbers>\d+(?:\.\d+)*)(?P<rest>.*)$").unwrap();
+}
+
+/// Split a string into runs of digits and runs of everything else.
+fn chunks(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != first.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+fn is_number(chunk: &str) -> bool {
+    chunk.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Compare numbers of any size without parsing them.
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Compare strings chunk by chunk with numbers compared by value
+/// (`rc2` < `rc10`). Numbers come before text.
+fn natural_compare(a: &str, b: &str) -> Ordering {
+    let mut a = chunks(a);
+    let mut b = chunks(b);
+
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (is_number(a), is_number(b)) {
+                (true, true) => compare_numbers(a, b),
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (false, false) => a.cmp(b),
+            },
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Anything after the numbers is either build metadata (`+build.5`) which
+/// doesn't change the order or a pre-release tag (`-rc.1`, `rc1`, `a1`) which
+/// comes before the release.
+fn compare_suffixes(a: &str, b: &str) -> Ordering {
+    let a = if a.starts_with('+') { "" } else { a };
+    let b = if b.starts_with('+') { "" } else { b };
+
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => natural_compare(a, b),
+    }
+}
+
+/// Check if a version name starts with numbers (`3.11`, `v18`) as opposed to
+/// being a name (`stable`)
+pub fn is_numbered(version: &str) -> bool {
+    NUMBERED_REGEX.is_match(version)
+}
+
+fn numbers(version: &str) -> Option<Vec<&str>> {
+    let captures = NUMBERED_REGEX.captures(version)?;
+    Some(captures.name("numbers")?.as_str().split('.').collect())
+}
+
+/// Check if the numbers of `name` are a prefix of the numbers of `version`
+/// (`3.11` & `3.11.7`). `None` is returned for names that aren't numbers
+/// (`stable`, `system`).
+pub fn is_prefix(name: &str, version: &str) -> Option<bool> {
+    let name = numbers(name)?;
+    let version = numbers(version)?;
+    Some(version.starts_with(&name))
+}
+
+/// Check if a version is a pre-release (`3.13.0-rc.1`, `1.22rc1`)
+pub fn is_pre_release(version: &str) -> bool {
+    NUMBERED_REGEX
+        .captures(version)
+        .is_some_and(|captures| !captures["rest"].is_empty() && !captures["rest"].starts_with('+'))
+}
+
+/// Order version names the way a human would:
+///
+/// - Numbers are compared by value (`9` < `10`, `3.9` < `3.10`).
+/// - Less precise versions come first (`3` < `3.0`).
+/// - Pre-releases come before their release (`3.13.0-rc.1` < `3.13.0`).
+/// - Names that don't start with a number (`stable`, `system`) come after the
+///   numbered versions and are compared naturally (`nightly-2024-01-09` <
+///   `nightly-2024-01-10`).
+///
+/// Names that are only equivalent (`v18` & `18`) are ordered by their text so
+/// that only identical names are equal.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let ordering = match (NUMBERED_REGEX.captures(a), NUMBERED_REGEX.captures(b)) {
+        (Some(a), Some(b)) => natural_compare(&a["numbers"], &b["numbers"])
+            .then_with(|| compare_suffixes(&a["rest"], &b["rest"])),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => natural_compare(a, b),
+    };
+
+    ordering.then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("1", "2" ; "single digit")]
+    #[test_case("9", "10" ; "numbers by value")]
+    #[test_case("3.9", "3.10" ; "minor by value")]
+    #[test_case("3.10.2", "3.10.10" ; "patch by value")]
+    #[test_case("2.7.18", "3.0" ; "major first")]
+    #[test_case("3", "3.0" ; "less precise first")]
+    #[test_case("3.11", "3.11.7" ; "less precise minor first")]
+    #[test_case("1.0.0-alpha", "1.0.0" ; "pre release before release")]
+    #[test_case("1.0.0-alpha", "1.0.0-alpha.1" ; "shorter pre release first")]
+    #[test_case("1.0.0-alpha.1", "1.0.0-beta" ; "pre release by name")]
+    #[test_case("1.0.0-rc.2", "1.0.0-rc.10" ; "pre release numbers by value")]
+    #[test_case("3.13.0a1", "3.13.0" ; "python style pre release")]
+    #[test_case("1.22rc1", "1.22" ; "go style pre release")]
+    #[test_case("1.22rc2", "1.22.0" ; "pre release before next patch")]
+    #[test_case("1.0.0", "1.0.0+build.5" ; "build metadata by text")]
+    #[test_case("007", "8" ; "leading zeros")]
+    #[test_case("18", "v18" ; "v prefix by text")]
+    #[test_case("v17", "18" ; "v prefix by value")]
+    #[test_case("99999999999999999999", "100000000000000000000" ; "huge numbers")]
+    #[test_case("21.5.0", "stable" ; "numbers before names")]
+    #[test_case("beta", "nightly" ; "names by text")]
+    #[test_case("nightly-2024-01-09", "nightly-2024-01-10" ; "names with dates")]
+    #[test_case("miniconda3-4.7", "miniconda3-23.1" ; "names with numbers by value")]
+    fn test_compare_less(a: &str, b: &str) {
+        assert_eq!(compare(a, b), Ordering::Less);
+        assert_eq!(compare(b, a), Ordering::Greater);
+    }
+
+    #[test_case("3.11.7" ; "numbered")]
+    #[test_case("1.0.0-rc.1" ; "pre release")]
+    #[test_case("stable" ; "name")]
+    fn test_compare_equal(a: &str) {
+        assert_eq!(compare(a, a), Ordering::Equal);
+    }
+
+    #[test_case("3.11", true, false ; "release")]
+    #[test_case("v18", true, false ; "v prefix")]
+    #[test_case("3.13.0-rc.1", true, true ; "pre release")]
+    #[test_case("1.0.0+build.5", true, false ; "build metadata")]
+    #[test_case("stable", false, false ; "name")]
+    fn test_kind(version: &str, numbered: bool, pre_release: bool) {
+        assert_eq!(is_numbered(version), numbered);
+        assert_eq!(is_pre_release(version), pre_release);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut versions = vec![
+            "stable",
+            "10",
+            "3.10",
+            "3.9",
+            "3",
+            "9",
+            "3.10.0-rc.1",
+            "beta",
+            "3.10.0",
+        ];
+        versions.sort_by(|a, b| compare(a, b));
+
+        assert_eq!(
+            versions,
+            vec![
+                "3",
+                "3.9",
+                "3.10",
+                "3.10.0-rc.1",
+                "3.10.0",
+                "9",
+                "10",
+                "beta",
+                "stable"
+            ]
+        );
+    }
+
+    #[test_case("3", "3.11.7", Some(true) ; "major")]
+    #[test_case("3.11", "3.11.7", Some(true) ; "minor")]
+    #[test_case("3.11.7", "3.11.7", Some(true) ; "exact")]
+    #[test_case("v18", "18.19.0", Some(true) ; "v prefix")]
+    #[test_case("3.1", "3.11.7", Some(false) ; "not a segment prefix")]
+    #[test_case("18", "20.1.0", Some(false) ; "different major")]
+    #[test_case("stable", "1.75.0", None ; "not a number")]
+    fn test_is_prefix(name: &str, version: &str, expected: Option<bool>) {
+        assert_eq!(is_prefix(name, version), expected);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::version_order;
+use std::cmp::Ordering;
+
+const OPERATORS: [(&str, Op); 5] = [
+    (">=", Op::GreaterOrEqual),
+    ("<=", Op::LessOrEqual),
+    (">", Op::Greater),
+    ("<", Op::Less),
+    ("=", Op::Equal),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Equal,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    /// `3.11.*`: any version that starts with these numbers
+    Wildcard,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Comparator {
+    op: Op,
+    version: String,
+}
+
+impl Comparator {
+    fn parse(text: &str) -> Self {
+        let text = text.trim();
+
+        if let Some(version) = text.strip_suffix(".*") {
+            return Comparator {
+                op: Op::Wildcard,
+                version: version.to_owned(),
+            };
+        }
+
+        let (op, version) = OPERATORS
+            .iter()
+            .find_map(|(prefix, op)| Some((*op, text.strip_prefix(prefix)?)))
+            .unwrap_or((Op::Equal, text));
+
+        Comparator {
+            op,
+            version: version.trim().to_owned(),
+        }
+    }
+
+    fn matches(&self, version: &str) -> bool {
+        if self.version.is_empty() {
+            return false;
+        }
+        // Names (`stable`) can't be compared to numbers
+        if self.op != Op::Equal && !version_order::is_numbered(version) {
+            return false;
+        }
+
+        // Partial versions stand for every version they start, like `18.*`:
+        // `<=3.12` includes `3.12.1` and `>3.12` doesn't
+        let is_prefix = version_order::is_prefix(&self.version, version) == Some(true);
+        let ordering = version_order::compare(version, &self.version);
+        match self.op {
+            Op::Equal => version == self.version,
+            Op::Greater => ordering == Ordering::Greater && !is_prefix,
+            Op::GreaterOrEqual => ordering != Ordering::Less,
+            Op::Less => ordering == Ordering::Less,
+            Op::LessOrEqual => ordering != Ordering::Greater || is_prefix,
+            Op::Wildcard => is_prefix,
+        }
+    }
+}
+
+/// The version a use file asks for. Either the name of a version (`18`) or
+/// constraints on the version separated by commas (`>=3.11, <3.13`, `18.*`).
+#[derive(Debug, PartialEq)]
+pub enum VersionReq {
+    Name(String),
+    Constraints(Vec<Comparator>),
+}
+
+impl VersionReq {
+    pub fn parse(text: &str) -> Self {
+        let is_constraint = text.trim_start().starts_with(['<', '>', '='])
+            || text.split(',').any(|part| part.trim().ends_with(".*"));
+
+        if is_constraint {
+            VersionReq::Constraints(text.split(',').map(Comparator::parse).collect())
+        } else {
+            VersionReq::Name(text.to_owned())
+        }
+    }
+
+    pub fn matches(&self, version: &str) -> bool {
+        match self {
+            VersionReq::Name(name) => version == name,
+            VersionReq::Constraints(comparators) => {
+                // Pre-releases are only picked when asked for
+                let allows_pre_release = comparators
+                    .iter()
+                    .any(|comparator| version_order::is_pre_release(&comparator.version));
+                (allows_pre_release || !version_order::is_pre_release(version))
+                    && comparators
+                        .iter()
+                        .all(|comparator| comparator.matches(version))
+            }
+        }
+    }
+
+    /// Pick the highest version that matches
+    pub fn select<'a>(&self, versions: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+        versions
+            .into_iter()
+            .filter(|version| self.matches(version))
+            .max_by(|a, b| version_order::compare(a, b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    const VERSIONS: [&str; 6] = ["3.9", "3.10", "3.11.7", "3.12.1", "3.13.0-rc.1", "stable"];
+
+    #[test_case("3.10", Some("3.10") ; "name")]
+    #[test_case("3.11", None ; "name is exact")]
+    #[test_case(">=3.10", Some("3.12.1") ; "names and pre releases are skipped")]
+    #[test_case(">=3.10, <3.12", Some("3.11.7") ; "range")]
+    #[test_case("<3.10", Some("3.9") ; "less than by value")]
+    #[test_case("3.12.*", Some("3.12.1") ; "wildcard")]
+    #[test_case("3.*", Some("3.12.1") ; "wildcard picks highest")]
+    #[test_case(">=3.13.0-rc.1", Some("3.13.0-rc.1") ; "pre release when asked for")]
+    #[test_case("=3.9", Some("3.9") ; "equal")]
+    #[test_case(">3.10", Some("3.12.1") ; "greater")]
+    #[test_case("=stable", Some("stable") ; "equal name")]
+    #[test_case(">=4, <5", None ; "nothing matches")]
+    #[test_case(">=", None ; "empty version")]
+    #[test_case("<=3.12", Some("3.12.1") ; "less or equal includes partial version")]
+    #[test_case("<=3.11.7", Some("3.11.7") ; "less or equal exact")]
+    #[test_case(">3.11, <3.12", None ; "greater excludes partial version")]
+    #[test_case(">3.12", None ; "greater than highest partial version")]
+    #[test_case(">=3.12", Some("3.12.1") ; "greater or equal includes partial version")]
+    #[test_case("<3.12", Some("3.11.7") ; "less excludes partial version")]
+    fn test_select(req: &str, expected: Option<&str>) {
+        assert_eq!(VersionReq::parse(req).select(VERSIONS), expected);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(VersionReq::parse("18"), VersionReq::Name("18".to_owned()));
+        assert_eq!(
+            VersionReq::parse(">= 3.11,<3.13"),
+            VersionReq::Constraints(vec![
+                Comparator {
+                    op: Op::GreaterOrEqual,
+                    version: "3.11".to_owned()
+                },
+                Comparator {
+                    op: Op::Less,
+                    version: "3.13".to_owned()
+                },
+            ])
+        );
+    }
+}
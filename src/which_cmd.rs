@@ -1,11 +1,11 @@
-use crate::command::find_selected_binary;
-use crate::environment::load_command_version_registry;
+use crate::command::{find_selected_binary, find_use_files, load_registry};
 use std::process;
 
 pub fn run(command: &str) -> anyhow::Result<()> {
-    let command_version_registry = load_command_version_registry()?;
+    let use_files = find_use_files(command);
+    let command_version_registry = load_registry(&use_files)?;
 
-    match find_selected_binary(&command_version_registry, command) {
+    match find_selected_binary(&command_version_registry, &use_files, command)? {
         Some(bin) => println!("{}", bin.to_str().unwrap()),
         None => {
             println!("command not found: {command}");
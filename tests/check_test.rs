@@ -0,0 +1,84 @@
+mod test_env;
+
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::fs;
+use std::io::Result as IoResult;
+use test_env::TestEnv;
+
+#[test]
+fn success_when_every_version_is_available() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.def("thingy", "1", &bin_path).assert().success();
+    fs::write(env.root.join("alt.toml"), "thingy = \"1\"\n")?;
+
+    env.alt()
+        .arg("check")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("thingy 1"));
+
+    Ok(())
+}
+
+#[test]
+fn success_without_use_file() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.alt()
+        .arg("check")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No use file"));
+
+    Ok(())
+}
+
+#[test]
+fn missing_version_suggests_scanned_versions() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-2", "this is thingy v2")?;
+    fs::write(env.root.join("alt.toml"), "thingy = \"2\"\n")?;
+
+    env.alt()
+        .arg("check")
+        .assert()
+        .code(4)
+        .stdout(predicate::str::contains(
+            "thingy 2: no matching version is defined",
+        ))
+        .stdout(predicate::str::contains(format!(
+            "try: alt def thingy 2 {}",
+            bin_path.display()
+        )));
+
+    Ok(())
+}
+
+#[test]
+fn missing_bin_is_reported_as_json() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.def("thingy", "1", &bin_path).assert().success();
+    fs::remove_file(&bin_path)?;
+    fs::write(env.root.join(".alt.toml"), "thingy = \">=1\"\n")?;
+
+    let output = env.alt().args(["check", "--format", "json"]).output()?;
+    assert_eq!(output.status.code(), Some(4));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(report["commands"][0]["command"], "thingy");
+    assert_eq!(report["commands"][0]["requested"], ">=1");
+    assert_eq!(report["commands"][0]["version"], "1");
+    assert_eq!(report["commands"][0]["status"], "not_found");
+    assert_eq!(
+        report["files"][0],
+        env.root.join(".alt.toml").to_str().unwrap()
+    );
+
+    Ok(())
+}
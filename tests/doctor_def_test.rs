@@ -127,3 +127,137 @@ fn remove_entry_for_non_executable_bin() -> IoResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn report_probed_version_mismatch() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy-1", "thingy version 1.2.3")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--probe", "--yes"])
+        .assert()
+        .success();
+
+    env.alt()
+        .args(["doctor", "--fix-mode", "never"])
+        .assert()
+        .success();
+
+    // Upgraded in place
+    env.create_stub_command("thingy-1", "thingy version 2.0.0")?;
+
+    env.alt()
+        .args(["doctor", "--fix-mode", "never"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Bin for thingy version 1.2.3 ("))
+        .stdout(predicate::str::contains("reports version 2.0.0."))
+        .stdout(predicate::str::contains("re-define it"));
+
+    Ok(())
+}
+
+#[test]
+fn update_probed_version_after_upgrade_in_place() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1", "thingy version 1.2.4")?;
+    fs::create_dir_all(env.root.join("alt-home"))?;
+    fs::write(
+        env.root.join("alt-home/defs.toml"),
+        format!(
+            "format_version = 2\n\n[commands.thingy.\"1\"]\npath = \"{}\"\nprobed_version = \"1.2.3\"\n",
+            bin_path.display()
+        ),
+    )?;
+
+    // Still matches its name
+    env.alt()
+        .args(["doctor", "--fix-mode", "never"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "now reports version 1.2.4 instead of 1.2.3",
+        ))
+        .stdout(predicate::str::contains("Problem").not());
+
+    env.alt()
+        .args(["doctor", "--fix-mode", "auto"])
+        .assert()
+        .success();
+
+    env.alt()
+        .args(["doctor", "--fix-mode", "never"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("now reports").not());
+    assert!(fs::read_to_string(env.root.join("alt-home/defs.toml"))?
+        .contains("probed_version = \"1.2.4\""));
+
+    Ok(())
+}
+
+#[test]
+fn report_versions_defined_without_probing() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-3", "thingy version 2.7.18")?;
+    env.def("thingy", "3", &bin_path).assert().success();
+
+    env.alt()
+        .args(["doctor", "--fix-mode", "never"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Bin for thingy version 3 ("))
+        .stdout(predicate::str::contains("reports version 2.7.18."));
+
+    Ok(())
+}
+
+#[test]
+fn report_invalid_use_file() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.def("thingy", "1", &bin_path).assert().success();
+
+    fs::write(env.root.join(".alt.toml"), "thingy = [\n")?;
+
+    env.alt()
+        .args(["doctor", "--fix-mode", "never"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Use file"))
+        .stdout(predicate::str::contains(".alt.toml:1:"));
+
+    Ok(())
+}
+
+#[test]
+fn report_system_entry_as_not_fixable() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-2", "this is thingy v2")?;
+    env.def("thingy", "2", &bin_path).assert().success();
+    let system_defs = env.root.join("system-defs.toml");
+    fs::write(&system_defs, "[thingy]\n\"1\" = \"/nonexistent/thingy\"\n")?;
+
+    env.alt()
+        .args(["doctor", "--fix-mode", "auto"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(format!(
+            "thingy version 1 is defined in {}, can't be fixed here.",
+            system_defs.display()
+        )))
+        .stdout(predicate::str::contains("Fixed 0/1."));
+
+    env.alt()
+        .arg("show")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("/nonexistent/thingy) [system]"));
+
+    Ok(())
+}
@@ -34,8 +34,14 @@ impl TryFrom<Output> for OutputSnapshot {
 #[test_case(vec!["help"]; "help command")]
 #[test_case(vec!["--help"]; "long help flag")]
 #[test_case(vec!["-h"]; "short help flag")]
+#[test_case(vec!["allow", "--help"]; "allow long help flag")]
+#[test_case(vec!["allow", "-h"]; "allow short help flag")]
+#[test_case(vec!["check", "--help"]; "check long help flag")]
+#[test_case(vec!["check", "-h"]; "check short help flag")]
 #[test_case(vec!["def", "--help"]; "def long help flag")]
 #[test_case(vec!["def", "-h"]; "def short help flag")]
+#[test_case(vec!["deny", "--help"]; "deny long help flag")]
+#[test_case(vec!["deny", "-h"]; "deny short help flag")]
 #[test_case(vec!["doctor", "--help"]; "doctor long help flag")]
 #[test_case(vec!["doctor", "-h"]; "doctor short help flag")]
 #[test_case(vec!["exec", "--help"]; "exec long help flag")]
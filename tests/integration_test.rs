@@ -1,7 +1,9 @@
 use assert_cmd::prelude::*;
+use predicates::prelude::*;
 mod test_env;
 use std::fs;
 use std::io::Result as IoResult;
+use std::os::unix::fs::PermissionsExt;
 use test_env::TestEnv;
 
 fn def_all(env: &TestEnv) -> IoResult<()> {
@@ -144,3 +146,496 @@ fn def_with_relative_path_works_after_dir_change() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn show_sorts_versions_by_value() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_v9_path = env.create_stub_command("thingy-9", "this is thingy v9")?;
+    let bin_v10_path = env.create_stub_command("thingy-10", "this is thingy v10")?;
+
+    env.def("thingy", "10", &bin_v10_path).assert().success();
+    env.def("thingy", "9", &bin_v9_path).assert().success();
+
+    env.alt()
+        .arg("show")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"(?s)thingy-9\).*thingy-10\)").unwrap());
+
+    Ok(())
+}
+
+#[test]
+fn version_env_is_set_when_running() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.root.join("stub-bins/thingy-1");
+    fs::write(&bin_path, "#!/bin/sh\nprintf '%s' \"$GREETING\"\n")?;
+    fs::set_permissions(&bin_path, fs::Permissions::from_mode(0o755))?;
+
+    env.def("thingy", "1", &bin_path).assert().success();
+    env._use("thingy", "1").assert().success();
+
+    let defs_path = env.root.join("alt-home/defs.toml");
+    let mut defs = fs::read_to_string(&defs_path)?;
+    defs.push_str("\n[commands.thingy.\"1\".env]\nGREETING = \"hello\"\n");
+    fs::write(&defs_path, defs)?;
+
+    env.command("thingy").assert().success().stdout("hello");
+
+    Ok(())
+}
+
+#[test]
+fn disabled_versions_are_not_run() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.def("thingy", "1", &bin_path).assert().success();
+    env._use("thingy", "1").assert().success();
+
+    let defs_path = env.root.join("alt-home/defs.toml");
+    let mut defs = fs::read_to_string(&defs_path)?;
+    defs.push_str("disabled = true\n");
+    fs::write(&defs_path, defs)?;
+
+    env.command("thingy").assert().failure();
+    env.alt()
+        .arg("show")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(disabled)"));
+
+    Ok(())
+}
+
+#[test]
+fn system_definitions_are_shared() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_v1_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    let bin_v2_path = env.create_stub_command("thingy-2", "this is thingy v2")?;
+    fs::write(
+        env.root.join("system-defs.toml"),
+        format!(
+            "[thingy]\n\"1\" = \"{}\"\n\"2\" = \"{}\"\n",
+            bin_v1_path.display(),
+            bin_v2_path.display()
+        ),
+    )?;
+    env.alt().arg("shim").assert().success();
+
+    env._use("thingy", "1").assert().success();
+    env.command("thingy")
+        .assert()
+        .success()
+        .stdout("this is thingy v1");
+
+    // Overriding a system version only changes the user's definitions file
+    env.def("thingy", "2", &bin_v1_path).assert().success();
+
+    env.alt().arg("show").assert().success().stdout(
+        predicate::str::contains("thingy-1) [system]")
+            .and(predicate::str::contains("thingy-2").not()),
+    );
+    assert!(fs::read_to_string(env.root.join("system-defs.toml"))?.contains("thingy-2"));
+
+    Ok(())
+}
+
+#[test]
+fn parallel_defs_are_all_kept() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+
+    std::thread::scope(|scope| {
+        for i in 0..20 {
+            let env = &env;
+            let bin_path = &bin_path;
+            scope.spawn(move || {
+                env.def("thingy", &i.to_string(), bin_path)
+                    .assert()
+                    .success();
+            });
+        }
+    });
+
+    let output = env.alt().arg("show").output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for i in 0..20 {
+        assert!(
+            stdout.contains(&format!("   {i} (")),
+            "version {i} is missing from:\n{stdout}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn invalid_use_file_falls_back_on_system_version() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    fs::write(env.root.join(".alt.toml"), "alfa = \"1\"\nbravo 2\n")?;
+
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa system version")
+        .stderr(predicate::str::contains(".alt.toml:2:7: "))
+        .stderr(predicate::str::contains("panicked").not());
+
+    Ok(())
+}
+
+#[test]
+fn invalid_use_file_can_fall_back_on_parent() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    env._use("alfa", "2").assert().success();
+    let subdir = env.root.join("subdir");
+    fs::create_dir(&subdir)?;
+    fs::write(subdir.join(".alt.toml"), "alfa = 3\n")?;
+    fs::create_dir_all(env.root.join("alt-home"))?;
+    fs::write(
+        env.root.join("alt-home/config.toml"),
+        "[use_file]\non_error = \"parent\"\n",
+    )?;
+
+    env.command("alfa")
+        .current_dir(&subdir)
+        .assert()
+        .success()
+        .stdout("alfa version 2");
+
+    Ok(())
+}
+
+#[test]
+fn invalid_use_file_can_fail() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    fs::write(env.root.join(".alt.toml"), "alfa = 3\n")?;
+    fs::create_dir_all(env.root.join("alt-home"))?;
+    fs::write(
+        env.root.join("alt-home/config.toml"),
+        "[use_file]\non_error = \"fail\"\n",
+    )?;
+
+    env.command("alfa")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains("Not running alfa"));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_system_defs_fall_back_on_system_version() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    env._use("alfa", "1").assert().success();
+    fs::write(env.root.join("system-defs.toml"), "[alfa\n")?;
+
+    let output = env.command("alfa").output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "alfa system version"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("system-defs.toml:1:"));
+    assert_eq!(stderr.lines().count(), 1);
+
+    fs::write(
+        env.root.join("alt-home/config.toml"),
+        "[use_file]\non_error = \"fail\"\n",
+    )?;
+    env.command("alfa")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains("Not running alfa"));
+
+    Ok(())
+}
+
+#[test]
+fn project_defs_are_relative_to_use_file() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    let tools = env.root.join("tools/bin");
+    fs::create_dir_all(&tools)?;
+    fs::write(tools.join("protoc"), "#!/bin/sh\nprintf 'project protoc'\n")?;
+    fs::set_permissions(tools.join("protoc"), fs::Permissions::from_mode(0o755))?;
+    fs::write(
+        env.root.join(".alt.toml"),
+        "protoc = \"3.21\"\n\n[defs.protoc]\n\"3.21\" = \"tools/bin/protoc\"\n",
+    )?;
+
+    // Allowing the file creates the shims of the commands it defines
+    env.alt().arg("allow").assert().success();
+
+    let subdir = env.root.join("subdir");
+    fs::create_dir(&subdir)?;
+    env.command("protoc")
+        .current_dir(&subdir)
+        .assert()
+        .success()
+        .stdout("project protoc");
+
+    // The global definitions are left alone
+    let defs = fs::read_to_string(env.root.join("alt-home/defs.toml"))?;
+    assert!(!defs.contains("protoc"));
+
+    Ok(())
+}
+
+#[test]
+fn project_defs_need_to_be_trusted() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    let bin = env.create_stub_command("project-alfa", "project alfa")?;
+    let use_file = env.root.join(".alt.toml");
+    let contents = format!(
+        "alfa = \"1\"\n\n[defs.alfa]\n\"1\" = \"{}\"\n",
+        bin.display()
+    );
+    fs::write(&use_file, &contents)?;
+
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 1")
+        .stderr(predicate::str::contains("run `alt allow` to trust them"));
+
+    env.alt().arg("allow").assert().success();
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("project alfa")
+        .stderr("");
+
+    // Any change revokes the trust
+    fs::write(&use_file, format!("{contents}# changed\n"))?;
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 1");
+
+    env.alt().arg("allow").assert().success();
+    env.alt().arg("deny").assert().success();
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 1");
+
+    Ok(())
+}
+
+#[test]
+fn shim_keeps_project_shims_from_elsewhere() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    let project = env.root.join("project");
+    fs::create_dir(&project)?;
+    let bin = env.create_stub_command("project-protoc", "project protoc")?;
+    fs::write(
+        project.join(".alt.toml"),
+        format!(
+            "protoc = \"3.21\"\n\n[defs.protoc]\n\"3.21\" = \"{}\"\n",
+            bin.display()
+        ),
+    )?;
+    env.alt()
+        .arg("allow")
+        .current_dir(&project)
+        .assert()
+        .success();
+
+    // Outside of the project
+    env.alt()
+        .arg("shim")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("protoc"));
+
+    env.command("protoc")
+        .current_dir(&project)
+        .assert()
+        .success()
+        .stdout("project protoc");
+
+    Ok(())
+}
+
+#[test]
+fn use_keeps_project_trusted() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    let bin = env.create_stub_command("project-protoc", "project protoc")?;
+    fs::write(
+        env.root.join(".alt.toml"),
+        format!(
+            "protoc = \"3.21\"\n\n[defs.protoc]\n\"3.21\" = \"{}\"\n",
+            bin.display()
+        ),
+    )?;
+    env.alt().arg("allow").assert().success();
+
+    env._use("alfa", "2").assert().success();
+
+    env.command("protoc")
+        .assert()
+        .success()
+        .stdout("project protoc")
+        .stderr("");
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 2");
+
+    Ok(())
+}
+
+#[test]
+fn personal_use_file_overrides_shared_use_file() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    fs::write(
+        env.root.join("alt.toml"),
+        "alfa = \"2\"\nbravo = \"2\"\ncharlie = \"2\"\n",
+    )?;
+    env._use("alfa", "3").assert().success();
+    env._use("charlie", "system").assert().success();
+
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 3");
+    env.command("bravo")
+        .assert()
+        .success()
+        .stdout("bravo version 2");
+    env.command("charlie")
+        .assert()
+        .success()
+        .stdout("charlie system version");
+
+    // Only the personal file was written
+    assert_eq!(
+        fs::read_to_string(env.root.join("alt.toml"))?,
+        "alfa = \"2\"\nbravo = \"2\"\ncharlie = \"2\"\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn use_shared_writes_shared_use_file() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    env.alt()
+        .args(["use", "--shared", "alfa", "2"])
+        .assert()
+        .success();
+
+    assert!(env.root.join("alt.toml").is_file());
+    assert!(!env.root.join(".alt.toml").exists());
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 2");
+
+    Ok(())
+}
+
+#[test]
+fn use_file_constraints_pick_highest_match() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    fs::write(
+        env.root.join("alt.toml"),
+        "alfa = \">=1, <3\"\nbravo = \">=4\"\n",
+    )?;
+
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 2");
+    env.alt()
+        .arg("show")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" * 2 ("));
+    env.command("bravo")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("alt: no version of bravo matches `>=4`\n");
+    env.alt()
+        .args(["which", "bravo"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no version of bravo matches `>=4`",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn use_with_constraints_keeps_them_in_the_use_file() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    env.alt()
+        .args(["use", "--shared", "alfa", ">=1, <3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Will now use alfa 2"));
+
+    assert_eq!(
+        fs::read_to_string(env.root.join("alt.toml"))?,
+        "alfa = \">=1, <3\"\n"
+    );
+    env.command("alfa")
+        .assert()
+        .success()
+        .stdout("alfa version 2");
+
+    Ok(())
+}
+
+#[test]
+fn use_reports_versions_that_dont_exist() -> IoResult<()> {
+    let env = TestEnv::new();
+    def_all(&env)?;
+
+    env._use("alfa", ">=4")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no version of alfa matches `>=4`"));
+    env._use("alfa", "42")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no version of alfa matches `42`"));
+    assert!(!env.root.join(".alt.toml").exists());
+
+    Ok(())
+}
@@ -0,0 +1,338 @@
+mod test_env;
+
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use std::io::Result as IoResult;
+use test_env::TestEnv;
+
+#[test]
+fn yes_adds_every_version() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy", "this is thingy system version")?;
+    env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.create_stub_command("thingy-2", "this is thingy v2")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--yes"])
+        .assert()
+        .success();
+
+    env._use("thingy", "2").assert().success();
+    env.command("thingy")
+        .assert()
+        .success()
+        .stdout("this is thingy v2");
+
+    Ok(())
+}
+
+#[test]
+fn scan_all_only_adds_commands_worth_adding() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.create_stub_command("thingy-2", "this is thingy v2")?;
+    env.create_stub_command("helper-1", "this is helper v1")?;
+    env.create_stub_command("wanted-1", "this is wanted v1")?;
+    std::fs::write(env.root.join(".alt.toml"), "wanted = \"1\"\n")?;
+
+    env.alt().args(["scan", "--yes"]).assert().success();
+
+    env.alt().arg("show").assert().success().stdout(
+        predicate::str::contains("thingy-2")
+            .and(predicate::str::contains("wanted-1"))
+            .and(predicate::str::contains("helper-1").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn filter_only_adds_matching_versions() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.create_stub_command("thingy-2", "this is thingy v2")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--yes", "--filter", "^2$"])
+        .assert()
+        .success();
+
+    env.alt().arg("show").assert().success().stdout(
+        predicate::str::contains("thingy-2").and(predicate::str::contains("thingy-1").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_does_not_add_anything() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy-1", "this is thingy v1")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("thingy 1"));
+
+    env.alt()
+        .arg("show")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("No commands are defined."));
+
+    Ok(())
+}
+
+#[test]
+fn dry_run_json_lists_new_versions() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new();
+
+    let bin_v1_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    let bin_v2_path = env.create_stub_command("thingy-2", "this is thingy v2")?;
+    env.def("thingy", "1", &bin_v1_path).assert().success();
+
+    let output = env
+        .alt()
+        .args(["scan", "thingy", "--dry-run", "--format", "json"])
+        .output()?;
+    assert!(output.status.success());
+
+    let candidates: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        candidates,
+        serde_json::json!([{
+            "command": "thingy",
+            "version": "2",
+            "path": bin_v2_path,
+        }])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn nothing_found_has_distinct_exit_code() {
+    let env = TestEnv::new();
+
+    env.alt()
+        .args(["scan", "does-not-exist", "--yes"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn duplicates_are_merged() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1.2", "this is thingy v1.2")?;
+    std::os::unix::fs::symlink(&bin_path, bin_path.with_file_name("thingy-1"))?;
+
+    env.alt()
+        .args(["scan", "thingy", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("thingy 1.2").and(predicate::str::contains("thingy 1 ").not()),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn non_utf8_file_names_are_reported_as_warnings() -> IoResult<()> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let env = TestEnv::new();
+
+    let bin_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    std::fs::write(
+        bin_path.with_file_name(OsStr::from_bytes(b"thingy-\xff")),
+        "",
+    )?;
+
+    env.alt()
+        .args(["scan", "thingy", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("thingy 1"))
+        .stderr(predicate::str::contains("file name is not valid UTF-8"));
+
+    Ok(())
+}
+
+#[test]
+fn update_adds_new_versions_and_removes_missing_ones() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_v1_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.def("thingy", "1", &bin_v1_path).assert().success();
+    std::fs::remove_file(&bin_v1_path)?;
+    env.create_stub_command("thingy-2", "this is thingy v2")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--update", "--yes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("+ thingy 2").and(predicate::str::contains("- thingy 1")));
+
+    env.alt().arg("show").assert().success().stdout(
+        predicate::str::contains("thingy-2").and(predicate::str::contains("thingy-1").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn update_repoints_moved_versions() -> Result<(), Box<dyn std::error::Error>> {
+    let env = TestEnv::new();
+
+    let bin_v1_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    let old_path = bin_v1_path.with_file_name("old-thingy-1");
+    std::fs::rename(&bin_v1_path, &old_path)?;
+    env.def("thingy", "1", &old_path).assert().success();
+    std::fs::rename(&old_path, &bin_v1_path)?;
+
+    let output = env
+        .alt()
+        .args([
+            "scan",
+            "thingy",
+            "--update",
+            "--dry-run",
+            "--format",
+            "json",
+        ])
+        .output()?;
+    assert!(output.status.success());
+
+    let changes: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        changes,
+        serde_json::json!([{
+            "change": "repoint",
+            "command": "thingy",
+            "version": "1",
+            "path": bin_v1_path,
+            "previous_path": old_path,
+        }])
+    );
+
+    env.alt()
+        .args(["scan", "thingy", "--update", "--yes"])
+        .assert()
+        .success();
+    env._use("thingy", "1").assert().success();
+    env.command("thingy")
+        .assert()
+        .success()
+        .stdout("this is thingy v1");
+
+    Ok(())
+}
+
+#[test]
+fn update_leaves_system_versions_alone() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let system_defs = env.root.join("system-defs.toml");
+    std::fs::write(&system_defs, "[thingy]\n\"1\" = \"/nonexistent/thingy\"\n")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--update", "--yes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- thingy 1").not())
+        .stderr(predicate::str::contains(format!(
+            "is defined in {}, can't be fixed here.",
+            system_defs.display()
+        )));
+
+    Ok(())
+}
+
+#[test]
+fn update_with_nothing_to_do() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    let bin_v1_path = env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.def("thingy", "1", &bin_v1_path).assert().success();
+
+    env.alt()
+        .args(["scan", "thingy", "--update", "--yes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Everything is up to date."));
+
+    Ok(())
+}
+
+#[test]
+fn update_all_only_adds_commands_worth_adding() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy-1", "this is thingy v1")?;
+    env.create_stub_command("thingy-2", "this is thingy v2")?;
+    env.create_stub_command("helper-1", "this is helper v1")?;
+
+    env.alt()
+        .args(["scan", "--update", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("stub-bins/thingy-2")
+                .and(predicate::str::contains("helper").not()),
+        );
+
+    env.alt()
+        .args(["scan", "--update", "--yes"])
+        .assert()
+        .success();
+
+    env.alt().arg("show").assert().success().stdout(
+        predicate::str::contains("thingy-2").and(predicate::str::contains("helper-1").not()),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn probe_suggests_precise_version_names() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy-1", "thingy version 1.2.3")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--probe", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("thingy 1.2.3"))
+        .stdout(predicate::str::contains("thingy 1 ").not());
+
+    Ok(())
+}
+
+#[test]
+fn probe_names_versions_after_their_precise_version() -> IoResult<()> {
+    let env = TestEnv::new();
+
+    env.create_stub_command("thingy-1", "thingy version 1.2.3")?;
+
+    env.alt()
+        .args(["scan", "thingy", "--probe", "--yes"])
+        .assert()
+        .success();
+
+    env._use("thingy", "1.2.3").assert().success();
+    env.command("thingy")
+        .assert()
+        .success()
+        .stdout("thingy version 1.2.3");
+
+    Ok(())
+}
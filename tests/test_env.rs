@@ -84,6 +84,7 @@ impl TestEnv {
         c.env_clear();
         c.env("ALT_HOME", self.root.join("alt-home"));
         c.env("ALT_SHIM_DIR", self.root.join("shims"));
+        c.env("ALT_SYSTEM_DEFS", self.root.join("system-defs.toml"));
         c.env(
             "PATH",
             env::join_paths([self.root.join("shims"), self.stub_bin_dir.clone()]).unwrap(),